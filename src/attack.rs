@@ -1,8 +1,14 @@
 use std::{f32::consts::PI, time::Duration};
 
-use bevy::utils::FloatOrd;
+use bevy::ecs::system::SystemParam;
 
-use crate::{prelude::*, ui::spawn_world_text};
+use crate::{
+    prelude::*,
+    targeting::{pick_target, TargetCandidate},
+    ui::spawn_world_text,
+};
+
+pub const WHIP_DAMAGE: f32 = 5.0;
 
 pub struct AttackPlugin;
 
@@ -22,10 +28,11 @@ impl Plugin for AttackPlugin {
     }
 }
 
-fn damage_enemy(
+pub fn damage_enemy(
     commands: &mut Commands,
     //Gross but makes font loading easier
     assets: &AssetServer,
+    damage_events: &mut EventWriter<DamageDealt>,
     enemy: &mut Enemy,
     position: &Transform,
     damage: f32,
@@ -35,18 +42,85 @@ fn damage_enemy(
         assets,
         position.translation.truncate(),
         &format!("{:?}", damage as i32),
+        Color::rgb(0.95, 0.2, 0.2),
     );
 
+    //Overkill doesn't count, lifesteal only heals off health actually taken
+    let dealt = damage.min(enemy.health.max(0.0));
     enemy.health -= damage;
+    damage_events.send(DamageDealt(dealt));
+}
+
+fn damage_prop(
+    commands: &mut Commands,
+    assets: &AssetServer,
+    prop: &mut Prop,
+    position: &GlobalTransform,
+    damage: f32,
+) {
+    spawn_world_text(
+        commands,
+        assets,
+        position.translation().truncate(),
+        &format!("{:?}", damage as i32),
+        Color::rgb(0.9, 0.9, 0.9),
+    );
+
+    prop.health -= damage;
+}
+
+/// Everything a weapon hit can land on
+#[derive(SystemParam)]
+struct HitTargets<'w, 's> {
+    //Gross but makes font loading easier
+    assets: Res<'w, AssetServer>,
+    enemies: Query<'w, 's, (&'static mut Enemy, &'static Transform)>,
+    props: Query<'w, 's, (&'static mut Prop, &'static GlobalTransform)>,
+    damage_events: EventWriter<'w, DamageDealt>,
+}
+
+impl HitTargets<'_, '_> {
+    /// Damages `entity` if it is an enemy or an unbroken prop, returns whether anything got hit
+    fn hit(&mut self, commands: &mut Commands, entity: Entity, damage: f32) -> bool {
+        if let Ok((mut enemy, transform)) = self.enemies.get_mut(entity) {
+            damage_enemy(
+                commands,
+                &self.assets,
+                &mut self.damage_events,
+                &mut enemy,
+                transform,
+                damage,
+            );
+            true
+        } else if let Ok((mut prop, transform)) = self.props.get_mut(entity) {
+            if prop.broken {
+                return false;
+            }
+            damage_prop(commands, &self.assets, &mut prop, transform, damage);
+            true
+        } else {
+            false
+        }
+    }
 }
 
-pub fn spawn_area_shot(commands: &mut Commands) -> Entity {
+//Nacho Avalanche buries the whole screen instead of just the crowds
+pub fn area_shot_targeting(evolved: bool) -> TargetingStrategy {
+    if evolved {
+        TargetingStrategy::RandomOnScreen
+    } else {
+        TargetingStrategy::DensestCluster
+    }
+}
+
+pub fn spawn_area_shot(commands: &mut Commands, targeting: TargetingStrategy) -> Entity {
     commands
         .spawn((
             SpatialBundle::default(),
             Name::new("Area Shot"),
             AreaShot {
                 timer: Timer::from_seconds(2.5, TimerMode::Repeating),
+                targeting,
             },
             GamePlayEntity,
             RngComponent::new(),
@@ -58,6 +132,7 @@ pub fn spawn_area_shot_bullet(
     commands: &mut Commands,
     assets: &AssetServer,
     spawn_pos: Vec2,
+    might: f32,
 ) -> Entity {
     info!("Spawning bullet");
     commands
@@ -75,7 +150,7 @@ pub fn spawn_area_shot_bullet(
             AreaShotBullet {
                 timer: Timer::from_seconds(0.25, TimerMode::Repeating),
                 lifetime: Timer::from_seconds(8.0, TimerMode::Once),
-                damage_per_second: 4.1,
+                damage_per_second: 4.1 * might,
             },
             Sensor,
             GamePlayEntity,
@@ -86,10 +161,9 @@ pub fn spawn_area_shot_bullet(
 
 fn area_shot_bullet(
     mut commands: Commands,
-    assets: Res<AssetServer>,
     mut bullets: Query<(Entity, &Transform, &Collider, &mut AreaShotBullet)>,
     rapier_context: Res<RapierContext>,
-    mut enemy: Query<(&mut Enemy, &Transform)>,
+    mut targets: HitTargets,
     time: Res<Time>,
 ) {
     for (bullet_entity, transform, collider, mut bullet) in &mut bullets {
@@ -100,21 +174,14 @@ fn area_shot_bullet(
 
         bullet.timer.tick(time.delta());
         if bullet.timer.just_finished() {
+            let damage = bullet.damage_per_second * bullet.timer.duration().as_secs_f32();
             rapier_context.intersections_with_shape(
                 transform.translation.truncate(),
                 0.0,
                 collider,
                 QueryFilter::new(),
                 |entity| {
-                    if let Ok((mut enemy, transform)) = enemy.get_mut(entity) {
-                        damage_enemy(
-                            &mut commands,
-                            &assets,
-                            &mut enemy,
-                            transform,
-                            bullet.damage_per_second * bullet.timer.duration().as_secs_f32(),
-                        );
-                    }
+                    targets.hit(&mut commands, entity, damage);
                     true
                 },
             );
@@ -122,14 +189,25 @@ fn area_shot_bullet(
     }
 }
 
-pub fn spawn_close_shot(commands: &mut Commands) -> Entity {
+//Burrito Barrage goes after the toughest enemy in view
+pub fn close_shot_targeting(evolved: bool) -> TargetingStrategy {
+    if evolved {
+        TargetingStrategy::HighestHealth
+    } else {
+        TargetingStrategy::Nearest
+    }
+}
+
+pub fn spawn_close_shot(commands: &mut Commands, targeting: TargetingStrategy) -> Entity {
     commands
         .spawn((
             SpatialBundle::default(),
             Name::new("Close Shot"),
             CloseShot {
                 timer: Timer::from_seconds(1.2, TimerMode::Repeating),
+                targeting,
             },
+            RngComponent::new(),
         ))
         .id()
 }
@@ -139,6 +217,7 @@ pub fn spawn_close_shot_bullet(
     assets: &AssetServer,
     spawn_pos: Vec2,
     direction: Vec2,
+    might: f32,
 ) -> Entity {
     commands
         .spawn((
@@ -154,7 +233,7 @@ pub fn spawn_close_shot_bullet(
             Name::new("Close Shot Bullet"),
             CloseShotBullet {
                 lifetime: Timer::from_seconds(5.0, TimerMode::Once),
-                damage: 2.0,
+                damage: 2.0 * might,
                 speed: 4.5,
                 direction,
             },
@@ -182,7 +261,10 @@ pub fn spawn_whip(commands: &mut Commands, assets: &AssetServer) -> Entity {
             },
             GamePlayEntity,
             Name::new("Whip"),
-            Whip { timer, damage: 5.0 },
+            Whip {
+                timer,
+                damage: WHIP_DAMAGE,
+            },
             TwoFrameAnimation {
                 frame_1: assets.load("ramen.png"),
                 frame_2: assets.load("ramen_2.png"),
@@ -218,11 +300,9 @@ pub fn whip_attack_facing(
 
 fn close_shot_bullet(
     mut commands: Commands,
-    //Gross but makes font loading easier
-    assets: Res<AssetServer>,
     mut bullets: Query<(Entity, &mut Transform, &Collider, &mut CloseShotBullet), Without<Enemy>>,
     rapier_context: Res<RapierContext>,
-    mut enemy: Query<(&mut Enemy, &Transform)>,
+    mut targets: HitTargets,
     time: Res<Time>,
 ) {
     for (bullet_entity, mut transform, collider, mut bullet) in &mut bullets {
@@ -239,8 +319,7 @@ fn close_shot_bullet(
             collider,
             QueryFilter::new(),
             |entity| {
-                if let Ok((mut enemy, transform)) = enemy.get_mut(entity) {
-                    damage_enemy(&mut commands, &assets, &mut enemy, transform, bullet.damage);
+                if targets.hit(&mut commands, entity, bullet.damage) {
                     commands.entity(bullet_entity).despawn_recursive();
                 }
                 true
@@ -249,31 +328,64 @@ fn close_shot_bullet(
     }
 }
 
+fn target_candidates(enemy: &Query<(&Transform, &Enemy)>) -> Vec<TargetCandidate> {
+    enemy
+        .iter()
+        .map(|(transform, enemy)| TargetCandidate {
+            position: transform.translation.truncate(),
+            health: enemy.health,
+        })
+        .collect()
+}
+
+/// Camera and cursor the close shot aims with
+#[derive(SystemParam)]
+struct Aim<'w, 's> {
+    camera: Query<'w, 's, &'static Transform, With<MainCamera>>,
+    cursor: Res<'w, CursorPosition>,
+    aim_mode: Res<'w, AimMode>,
+}
+
+impl Aim<'_, '_> {
+    fn manual_target(&self) -> Option<Vec2> {
+        (*self.aim_mode == AimMode::Manual).then_some(self.cursor.world_position)
+    }
+
+    fn camera_center(&self) -> Vec2 {
+        self.camera.single().translation.truncate()
+    }
+}
+
 fn close_shot_attack(
     mut commands: Commands,
     assets: Res<AssetServer>,
-    mut close_shots: Query<(&GlobalTransform, &mut CloseShot)>,
-    enemy: Query<&Transform, With<Enemy>>,
+    mut close_shots: Query<(&GlobalTransform, &mut CloseShot, &mut RngComponent)>,
+    enemy: Query<(&Transform, &Enemy)>,
+    player: Query<&Player>,
+    aim: Aim,
     time: Res<Time>,
 ) {
-    for (transform, mut close_shot) in &mut close_shots {
+    let player = player.single();
+    let camera = aim.camera_center();
+
+    for (transform, mut close_shot, mut rng) in &mut close_shots {
         close_shot.timer.tick(time.delta());
         if close_shot.timer.just_finished() {
-            if let Some(closest_enemy) = enemy.iter().min_by_key(|enemy_transform| {
-                FloatOrd(Vec2::length(
-                    transform.translation().truncate() - enemy_transform.translation.truncate(),
-                ))
-            }) {
-                let direction = (closest_enemy.translation.truncate()
-                    - transform.translation().truncate())
-                .normalize();
-
-                spawn_close_shot_bullet(
-                    &mut commands,
-                    &assets,
-                    transform.translation().truncate(),
-                    direction,
-                );
+            let origin = transform.translation().truncate();
+            let target = aim.manual_target().or_else(|| {
+                pick_target(
+                    close_shot.targeting,
+                    origin,
+                    camera,
+                    &target_candidates(&enemy),
+                    &mut rng,
+                )
+            });
+
+            if let Some(target) = target {
+                let direction = (target - origin).try_normalize().unwrap_or(Vec2::X);
+
+                spawn_close_shot_bullet(&mut commands, &assets, origin, direction, player.might);
             }
         }
     }
@@ -281,29 +393,41 @@ fn close_shot_attack(
 
 fn area_shot_attack(
     mut commands: Commands,
-    mut close_shots: Query<(&GlobalTransform, &mut AreaShot, &mut RngComponent)>,
+    mut area_shots: Query<(&GlobalTransform, &mut AreaShot, &mut RngComponent)>,
+    enemy: Query<(&Transform, &Enemy)>,
+    player: Query<&Player>,
+    camera: Query<&Transform, With<MainCamera>>,
     assets: Res<AssetServer>,
     time: Res<Time>,
 ) {
-    for (transform, mut close_shot, mut rng) in &mut close_shots {
-        close_shot.timer.tick(time.delta());
-        if close_shot.timer.just_finished() {
-            let location = Vec2::new(rng.f32_normalized(), rng.f32_normalized()).normalize() * 7.0;
-            let offset = Vec2::new(rng.f32_normalized(), rng.f32_normalized()) * 1.5;
+    let player = player.single();
+    let camera = camera.single();
 
-            spawn_area_shot_bullet(
-                &mut commands,
-                &assets,
-                transform.translation().truncate() + location + offset,
+    for (transform, mut area_shot, mut rng) in &mut area_shots {
+        area_shot.timer.tick(time.delta());
+        if area_shot.timer.just_finished() {
+            let origin = transform.translation().truncate();
+            let target = pick_target(
+                area_shot.targeting,
+                origin,
+                camera.translation.truncate(),
+                &target_candidates(&enemy),
+                &mut rng,
             );
+
+            //Nothing worth aiming at, just drop it somewhere nearby
+            let location = target.unwrap_or_else(|| {
+                origin + Vec2::new(rng.f32_normalized(), rng.f32_normalized()).normalize() * 7.0
+            });
+            let offset = Vec2::new(rng.f32_normalized(), rng.f32_normalized()) * 0.5;
+
+            spawn_area_shot_bullet(&mut commands, &assets, location + offset, player.might);
         }
     }
 }
 
 fn whip_attack(
     mut commands: Commands,
-    //Gross but makes font loading easier
-    assets: Res<AssetServer>,
     mut whips: Query<(
         &Collider,
         &GlobalTransform,
@@ -311,10 +435,13 @@ fn whip_attack(
         &mut TwoFrameAnimation,
         &mut Visibility,
     )>,
-    mut enemy: Query<(&mut Enemy, &Transform)>,
+    mut targets: HitTargets,
+    player: Query<&Player>,
     rapier_context: Res<RapierContext>,
     time: Res<Time>,
 ) {
+    let might = player.single().might;
+
     for (collider, transform, mut whip, mut animation, mut visibility) in &mut whips {
         whip.timer.tick(time.delta());
 
@@ -336,9 +463,7 @@ fn whip_attack(
                 collider,
                 QueryFilter::new(),
                 |entity| {
-                    if let Ok((mut enemy, transform)) = enemy.get_mut(entity) {
-                        damage_enemy(&mut commands, &assets, &mut enemy, transform, whip.damage);
-                    }
+                    targets.hit(&mut commands, entity, whip.damage * might);
                     true
                 },
             );
@@ -11,6 +11,9 @@ use bevy::{
     },
     sprite::MaterialMesh2dBundle,
 };
+//World size of the quad the main render is drawn on, the final camera letterboxes around it
+const RENDER_QUAD: Vec2 = Vec2::new(16.0, 9.0);
+
 pub struct GameCameraPlugin;
 
 impl Plugin for GameCameraPlugin {
@@ -18,6 +21,13 @@ impl Plugin for GameCameraPlugin {
         app.add_startup_system(spawn_camera)
             .add_system(update_cursor)
             .init_resource::<CursorPosition>()
+            .init_resource::<AimMode>()
+            .add_system(spawn_crosshair.in_schedule(OnEnter(GameState::StartingLoop)))
+            .add_system(
+                update_crosshair
+                    .after(update_cursor)
+                    .in_set(OnUpdate(GameState::Gameplay)),
+            )
             .add_system(
                 camera_follow
                     .after(player_movement)
@@ -27,16 +37,74 @@ impl Plugin for GameCameraPlugin {
     }
 }
 
-fn update_cursor(mut cursor: ResMut<CursorPosition>, windows: Query<&Window>) {
+fn update_cursor(
+    mut cursor: ResMut<CursorPosition>,
+    windows: Query<&Window>,
+    main_camera: Query<(&Camera, &GlobalTransform), With<MainCamera>>,
+    final_camera: Query<(&Camera, &GlobalTransform), With<FinalCamera>>,
+) {
     let window = windows.single();
 
-    if let Some(screen_position) = window.cursor_position() {
-        let screen_position = screen_position / Vec2::new(window.width(), window.height());
+    if let Some(window_position) = window.cursor_position() {
+        let screen_position = window_position / Vec2::new(window.width(), window.height());
+
+        //Find the spot on the render quad first so the letterbox bars don't skew the aim
+        let (final_camera, final_transform) = final_camera.single();
+        let quad_position = final_camera
+            .viewport_to_world_2d(final_transform, window_position)
+            .map(|position| position / RENDER_QUAD + 0.5);
+
+        //The main camera renders to an image drawn on that quad, so scale into its viewport
+        let (camera, transform) = main_camera.single();
+        if let Some(world_position) = quad_position.and_then(|quad_position| {
+            camera.viewport_to_world_2d(
+                transform,
+                quad_position * Vec2::new(RENDER_WIDTH, RENDER_HEIGHT),
+            )
+        }) {
+            cursor.world_position = world_position;
+        }
+
         let screen_position = Vec2::new(screen_position.x, 1.0 - screen_position.y);
         cursor.screen_position = screen_position;
     }
 }
 
+fn spawn_crosshair(mut commands: Commands, assets: Res<AssetServer>) {
+    commands.spawn((
+        SpriteBundle {
+            transform: Transform::from_xyz(0.0, 0.0, 200.0),
+            texture: assets.load("crosshair.png"),
+            sprite: Sprite {
+                custom_size: Some(Vec2::splat(32.0 * PIXEL_TO_WORLD)),
+                ..default()
+            },
+            visibility: Visibility::Hidden,
+            ..default()
+        },
+        Crosshair,
+        GamePlayEntity,
+        Name::new("Crosshair"),
+    ));
+}
+
+fn update_crosshair(
+    mut crosshair: Query<(&mut Transform, &mut Visibility), With<Crosshair>>,
+    cursor: Res<CursorPosition>,
+    aim_mode: Res<AimMode>,
+) {
+    for (mut transform, mut visibility) in &mut crosshair {
+        transform.translation.x = cursor.world_position.x;
+        transform.translation.y = cursor.world_position.y;
+
+        *visibility = if *aim_mode == AimMode::Manual {
+            Visibility::Visible
+        } else {
+            Visibility::Hidden
+        };
+    }
+}
+
 fn camera_follow(
     player: Query<&Transform, (With<Player>, Without<MainCamera>)>,
     mut camera: Query<&mut Transform, With<MainCamera>>,
@@ -97,7 +165,7 @@ fn spawn_camera(
         UiCameraConfig { show_ui: false },
     ));
 
-    let quad_handle = meshes.add(Mesh::from(shape::Quad::new(Vec2::new(16.0, 9.0))));
+    let quad_handle = meshes.add(Mesh::from(shape::Quad::new(RENDER_QUAD)));
 
     let material_handle = materials.add(ColorMaterial {
         texture: Some(image_handle.clone()),
@@ -125,8 +193,8 @@ fn spawn_camera(
     let mut camera = Camera2dBundle::default();
     camera.camera.order = 999;
     camera.projection.scaling_mode = ScalingMode::AutoMin {
-        min_width: 16.0,
-        min_height: 9.0,
+        min_width: RENDER_QUAD.x,
+        min_height: RENDER_QUAD.y,
     };
 
     commands.spawn((
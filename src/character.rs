@@ -0,0 +1,45 @@
+use crate::{prelude::*, storage::parse_baked};
+
+pub struct CharacterPlugin;
+
+impl Plugin for CharacterPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(load_characters())
+            .init_resource::<SelectedCharacter>();
+    }
+}
+
+fn load_characters() -> Characters {
+    let characters: Vec<Character> =
+        parse_baked("characters.ron", include_str!("../assets/characters.ron"));
+    assert!(!characters.is_empty(), "Need at least one character");
+    Characters(characters)
+}
+
+impl CharacterPassive {
+    pub fn description(&self) -> String {
+        match self {
+            CharacterPassive::HealthPerLevel(amount) => {
+                format!("+{:.0}% Max Health per level", amount * 100.0)
+            }
+            CharacterPassive::SpeedPerLevel(amount) => {
+                format!("+{:.0}% Speed per level", amount * 100.0)
+            }
+            CharacterPassive::ArmorPerLevel(amount) => format!("+{} Armor per level", amount),
+            CharacterPassive::RegenPerLevel(amount) => format!("+{} Regen per level", amount),
+        }
+    }
+
+    pub fn apply(&self, player: &mut Player) {
+        match *self {
+            CharacterPassive::HealthPerLevel(amount) => {
+                let increase = player.max_health * amount;
+                player.health += increase;
+                player.max_health += increase;
+            }
+            CharacterPassive::SpeedPerLevel(amount) => player.speed *= 1.0 + amount,
+            CharacterPassive::ArmorPerLevel(amount) => player.armor += amount,
+            CharacterPassive::RegenPerLevel(amount) => player.regen += amount,
+        }
+    }
+}
@@ -0,0 +1,300 @@
+use bevy::time::Stopwatch;
+use strum::IntoEnumIterator;
+
+use crate::{prelude::*, upgrade::weighted_pick};
+
+//Seconds before the first slot stops and between each following slot
+const SPIN_TIME: f32 = 1.2;
+const SLOT_DELAY: f32 = 0.5;
+const TICK_TIME: f32 = 0.07;
+
+pub struct ChestPlugin;
+
+impl Plugin for ChestPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(ChestReward {
+            rewards: Vec::new(),
+            spin: Stopwatch::new(),
+            tick: Timer::from_seconds(TICK_TIME, TimerMode::Repeating),
+        })
+        .add_systems(
+            (roll_chest_rewards, spawn_chest_ui.after(roll_chest_rewards))
+                .in_schedule(OnEnter(GameState::Chest)),
+        )
+        .add_systems(
+            (spin_chest_slots, chest_collect_button_system).in_set(OnUpdate(GameState::Chest)),
+        )
+        .add_system(despawn_chest_ui.in_schedule(OnExit(GameState::Chest)));
+    }
+}
+
+//Mostly one, sometimes three and rarely five, luck pushes toward more
+fn reward_count(luck: f32, rng: &mut GlobalRng) -> usize {
+    let roll = rng.f32();
+    if roll < 0.05 + luck * 0.1 {
+        5
+    } else if roll < 0.3 + luck * 0.2 {
+        3
+    } else {
+        1
+    }
+}
+
+fn roll_chest_rewards(
+    mut reward: ResMut<ChestReward>,
+    inventory: Res<Inventory>,
+    offers: Res<LevelUpOffers>,
+    player: Query<&Player>,
+    mut rng: ResMut<GlobalRng>,
+    audio: Res<Audio>,
+    assets: Res<AssetServer>,
+) {
+    let count = reward_count(player.single().luck, &mut rng);
+
+    reward.rewards.clear();
+    reward.spin.reset();
+    //Picks land here first so later picks see the slots and levels earlier ones used up
+    let mut provisional = inventory.clone();
+    //A ready evolution is always the first prize
+    if let Some(evolution) =
+        WeaponUpgrade::iter().find(|upgrade| inventory.evolution_ready(upgrade))
+    {
+        provisional.add(&evolution);
+        reward.rewards.push(evolution);
+    }
+
+    //Distinct picks so two levels of one weapon never land in the same frame
+    while reward.rewards.len() < count {
+        let mut pool: Vec<WeaponUpgrade> = WeaponUpgrade::iter()
+            .filter(|upgrade| {
+                !offers.banished.contains(upgrade)
+                    && !reward.rewards.contains(upgrade)
+                    && provisional.can_offer(upgrade)
+            })
+            .collect();
+        let upgrade = if pool.is_empty() {
+            let fallback = [WeaponUpgrade::Heal, WeaponUpgrade::Gold];
+            fallback[reward.rewards.len() % fallback.len()].clone()
+        } else {
+            weighted_pick(&mut pool, &mut rng)
+        };
+        provisional.add(&upgrade);
+        reward.rewards.push(upgrade);
+    }
+
+    audio.play_with_settings(
+        assets.load("chest_open.wav"),
+        PlaybackSettings {
+            repeat: false,
+            volume: 0.7,
+            speed: 1.0,
+        },
+    );
+}
+
+fn spin_chest_slots(
+    mut reward: ResMut<ChestReward>,
+    mut slots: Query<(&mut Text, &ChestSlotUI)>,
+    mut button: Query<&mut Visibility, With<ChestCollectButtonUI>>,
+    mut rng: ResMut<GlobalRng>,
+    audio: Res<Audio>,
+    assets: Res<AssetServer>,
+    time: Res<Time>,
+) {
+    reward.spin.tick(time.delta());
+    reward.tick.tick(time.delta());
+
+    let elapsed = reward.spin.elapsed_secs();
+    let names = WeaponUpgrade::iter()
+        .filter(|upgrade| !upgrade.is_fallback())
+        .collect::<Vec<_>>();
+
+    let mut spinning = false;
+    for (mut text, slot) in &mut slots {
+        let Some(prize) = reward.rewards.get(slot.0) else {
+            continue;
+        };
+        if elapsed >= SPIN_TIME + SLOT_DELAY * slot.0 as f32 {
+            text.sections[0].value = prize.name().to_string();
+            text.sections[0].style.color = if prize.is_evolution() {
+                Color::ORANGE_RED
+            } else {
+                Color::GOLD
+            };
+        } else {
+            spinning = true;
+            if reward.tick.just_finished() {
+                let index = (rng.f32() * names.len() as f32) as usize % names.len();
+                text.sections[0].value = names[index].name().to_string();
+            }
+        }
+    }
+
+    if spinning && reward.tick.just_finished() {
+        audio.play_with_settings(
+            assets.load("chest_tick.wav"),
+            PlaybackSettings {
+                repeat: false,
+                volume: 0.3,
+                speed: 1.0,
+            },
+        );
+    }
+
+    if !spinning {
+        for mut visibility in &mut button {
+            *visibility = Visibility::Inherited;
+        }
+    }
+}
+
+fn chest_collect_button_system(
+    mut interaction_query: Query<
+        (&Interaction, &mut BackgroundColor),
+        (
+            Changed<Interaction>,
+            With<Button>,
+            With<ChestCollectButtonUI>,
+        ),
+    >,
+    reward: Res<ChestReward>,
+    mut upgrade_event: EventWriter<UpgradeSelected>,
+    mut next_state: ResMut<NextState<GameState>>,
+) {
+    for (interaction, mut color) in &mut interaction_query {
+        match *interaction {
+            Interaction::Clicked => {
+                *color = Color::RED.into();
+                //Same path as a level up pick so every apply system sees it
+                for upgrade in &reward.rewards {
+                    upgrade_event.send(UpgradeSelected(upgrade.clone()));
+                }
+                next_state.set(GameState::Gameplay);
+            }
+            Interaction::Hovered => {
+                *color = Color::GREEN.into();
+            }
+            Interaction::None => {
+                *color = Color::CRIMSON.into();
+            }
+        }
+    }
+}
+
+fn spawn_chest_ui(mut commands: Commands, assets: Res<AssetServer>, reward: Res<ChestReward>) {
+    let font = assets.load("fonts/pointfree.ttf");
+
+    let chest_parent = (
+        NodeBundle {
+            style: Style {
+                size: Size::new(Val::Percent(100.0), Val::Percent(100.0)),
+                position_type: PositionType::Absolute,
+                align_items: AlignItems::Center,
+                justify_content: JustifyContent::Center,
+                ..default()
+            },
+            ..default()
+        },
+        ChestUI,
+    );
+
+    let chest_popup = NodeBundle {
+        style: Style {
+            size: Size::new(Val::Percent(60.0), Val::Percent(70.0)),
+            position_type: PositionType::Relative,
+            align_items: AlignItems::Center,
+            flex_direction: FlexDirection::Column,
+            justify_content: JustifyContent::SpaceAround,
+            ..default()
+        },
+        background_color: Color::DARK_GRAY.into(),
+        ..default()
+    };
+
+    let title_text = TextBundle::from_section(
+        "Treasure!",
+        TextStyle {
+            font: font.clone(),
+            font_size: 48.0,
+            color: Color::rgb(0.9, 0.9, 0.9),
+        },
+    );
+
+    let chest_image = ImageBundle {
+        style: Style {
+            size: Size::new(Val::Px(96.0), Val::Px(84.0)),
+            ..default()
+        },
+        image: assets.load("chest.png").into(),
+        ..default()
+    };
+
+    let slot_height = 60.0 / reward.rewards.len().max(1) as f32;
+
+    let button = (
+        ButtonBundle {
+            style: Style {
+                size: Size::new(Val::Percent(40.0), Val::Percent(10.0)),
+                justify_content: JustifyContent::Center,
+                align_items: AlignItems::Center,
+                ..default()
+            },
+            background_color: Color::CRIMSON.into(),
+            //Shown once every slot has stopped
+            visibility: Visibility::Hidden,
+            ..default()
+        },
+        ChestCollectButtonUI,
+    );
+
+    let button_text = TextBundle::from_section(
+        "Collect",
+        TextStyle {
+            font: font.clone(),
+            font_size: 32.0,
+            color: Color::rgb(0.9, 0.9, 0.9),
+        },
+    );
+
+    commands.spawn(chest_parent).with_children(|commands| {
+        commands.spawn(chest_popup).with_children(|commands| {
+            commands.spawn(title_text);
+            commands.spawn(chest_image);
+            for i in 0..reward.rewards.len() {
+                commands
+                    .spawn(NodeBundle {
+                        style: Style {
+                            size: Size::new(Val::Percent(70.0), Val::Percent(slot_height * 0.8)),
+                            justify_content: JustifyContent::Center,
+                            align_items: AlignItems::Center,
+                            ..default()
+                        },
+                        background_color: Color::rgb(0.15, 0.15, 0.15).into(),
+                        ..default()
+                    })
+                    .with_children(|commands| {
+                        commands.spawn((
+                            TextBundle::from_section(
+                                "?",
+                                TextStyle {
+                                    font: font.clone(),
+                                    font_size: 28.0,
+                                    color: Color::rgb(0.9, 0.9, 0.9),
+                                },
+                            ),
+                            ChestSlotUI(i),
+                        ));
+                    });
+            }
+            commands.spawn(button).with_children(|commands| {
+                commands.spawn(button_text);
+            });
+        });
+    });
+}
+
+fn despawn_chest_ui(mut commands: Commands, ui: Query<Entity, With<ChestUI>>) {
+    for ui in &ui {
+        commands.entity(ui).despawn_recursive();
+    }
+}
@@ -1,4 +1,14 @@
-use crate::prelude::*;
+use crate::{exp::spawn_exp_orb, prelude::*, prop::spawn_pickup, ui::spawn_world_text};
+
+//Seconds between elites, each one drops a chest
+pub const ELITE_INTERVAL: f32 = 60.0;
+const ELITE_HEALTH: f32 = 12.0;
+const ELITE_SCALE: f32 = 1.6;
+//Seconds between bosses, they drop a chest too
+pub const BOSS_INTERVAL: f32 = 300.0;
+const BOSS_HEALTH: f32 = 80.0;
+const BOSS_DAMAGE: f32 = 2.0;
+const BOSS_SCALE: f32 = 2.6;
 
 pub struct EnemyPlugin;
 
@@ -20,17 +30,20 @@ impl Plugin for EnemyPlugin {
 fn despawn_enemy(
     mut commands: Commands,
     player: Query<&Transform, (With<Player>, Without<Enemy>)>,
-    enemy: Query<(Entity, &Transform), With<Enemy>>,
+    enemy: Query<(Entity, &Transform, &Enemy)>,
 ) {
     let player = player.single();
 
-    for (enemy, transform) in &enemy {
-        if Vec2::distance(
-            player.translation.truncate(),
-            transform.translation.truncate(),
-        ) > 30.0
+    for (entity, transform, enemy) in &enemy {
+        //Don't lose a chest because it fell behind
+        if !enemy.elite
+            && !enemy.boss
+            && Vec2::distance(
+                player.translation.truncate(),
+                transform.translation.truncate(),
+            ) > 30.0
         {
-            commands.entity(enemy).despawn_recursive();
+            commands.entity(entity).despawn_recursive();
         }
     }
 }
@@ -45,6 +58,11 @@ fn spawn_enemy(
 ) {
     let player_transform = player.single();
 
+    //A stage with no waves is just a peaceful walk
+    if wave_manager.waves.is_empty() {
+        return;
+    }
+
     wave_manager.global_time.tick(time.delta());
 
     let current_wave = (wave_manager.global_time.elapsed_secs() / 20.0) as usize;
@@ -75,37 +93,109 @@ fn spawn_enemy(
             enemy.health *= 1.3_f32.powf(wave_buf as f32);
 
             target_translation += player_transform.translation.truncate().extend(0.0);
-            commands.spawn((
-                SpriteBundle {
-                    texture: assets.load(&wave.to_spawn.asset),
-                    sprite: Sprite {
-                        custom_size: Some(Vec2::new(63.0 * PIXEL_TO_WORLD, 113.0 * PIXEL_TO_WORLD)),
-                        ..default()
-                    },
-                    transform: Transform::from_translation(target_translation),
-                    ..default()
-                },
+            spawn_enemy_entity(
+                &mut commands,
+                &assets,
+                &mut global_rng,
                 enemy,
-                Name::new("Enemy"),
-                RngComponent::from(&mut global_rng),
-                RigidBody::Dynamic,
-                LockedAxes::ROTATION_LOCKED_Z,
-                Damping {
-                    linear_damping: 100.0,
-                    angular_damping: 1.0,
-                },
-                GamePlayEntity,
-                Collider::capsule(Vec2::new(0.0, 0.55), Vec2::new(0.0, -0.55), 0.8),
-            ));
+                target_translation,
+            );
         }
     }
+
+    wave_manager.elite_timer.tick(time.delta());
+    if wave_manager.elite_timer.just_finished() {
+        let wave = &wave_manager.waves[wave_index];
+        let mut enemy = wave.to_spawn.clone();
+        enemy.health *= ELITE_HEALTH * 1.3_f32.powf(wave_buf as f32);
+        enemy.elite = true;
+
+        let target_translation = 22.0
+            * Vec2::new(global_rng.f32_normalized(), global_rng.f32_normalized()).normalize()
+            + player_transform.translation.truncate();
+        spawn_enemy_entity(
+            &mut commands,
+            &assets,
+            &mut global_rng,
+            enemy,
+            target_translation.extend(100.0),
+        );
+    }
+
+    wave_manager.boss_timer.tick(time.delta());
+    if wave_manager.boss_timer.just_finished() {
+        let wave = &wave_manager.waves[wave_index];
+        let mut enemy = wave.to_spawn.clone();
+        enemy.health *= BOSS_HEALTH * 1.3_f32.powf(wave_buf as f32);
+        enemy.damage *= BOSS_DAMAGE;
+        enemy.boss = true;
+
+        let target_translation = 22.0
+            * Vec2::new(global_rng.f32_normalized(), global_rng.f32_normalized()).normalize()
+            + player_transform.translation.truncate();
+        spawn_enemy_entity(
+            &mut commands,
+            &assets,
+            &mut global_rng,
+            enemy,
+            target_translation.extend(100.0),
+        );
+    }
+}
+
+pub fn spawn_enemy_entity(
+    commands: &mut Commands,
+    assets: &AssetServer,
+    global_rng: &mut GlobalRng,
+    enemy: Enemy,
+    translation: Vec3,
+) {
+    let (scale, color) = if enemy.boss {
+        (BOSS_SCALE, Color::rgb(1.0, 0.4, 0.4))
+    } else if enemy.elite {
+        (ELITE_SCALE, Color::rgb(1.0, 0.8, 0.3))
+    } else {
+        (1.0, Color::WHITE)
+    };
+
+    commands.spawn((
+        SpriteBundle {
+            texture: assets.load(&enemy.asset),
+            sprite: Sprite {
+                custom_size: Some(Vec2::new(63.0 * PIXEL_TO_WORLD, 113.0 * PIXEL_TO_WORLD) * scale),
+                color,
+                ..default()
+            },
+            transform: Transform::from_translation(translation),
+            ..default()
+        },
+        enemy,
+        Name::new("Enemy"),
+        RngComponent::from(global_rng),
+        RigidBody::Dynamic,
+        LockedAxes::ROTATION_LOCKED_Z,
+        Damping {
+            linear_damping: 100.0,
+            angular_damping: 1.0,
+        },
+        GamePlayEntity,
+        Collider::capsule(
+            Vec2::new(0.0, 0.55 * scale),
+            Vec2::new(0.0, -0.55 * scale),
+            0.8 * scale,
+        ),
+        CollisionGroups::new(ENEMY_GROUP, Group::ALL),
+    ));
 }
 
 fn enemy_damage_player(
+    mut commands: Commands,
+    //Gross but makes font loading easier
+    assets: Res<AssetServer>,
+    audio: Res<Audio>,
     enemies: Query<(&Collider, &GlobalTransform, &Enemy)>,
-    mut player: Query<&mut Player>,
+    mut player: Query<(&Transform, &mut Player, &mut Invulnerability, &Dash)>,
     rapier_context: Res<RapierContext>,
-    time: Res<Time>,
 ) {
     for (collider, transform, enemy) in &enemies {
         rapier_context.intersections_with_shape(
@@ -114,8 +204,32 @@ fn enemy_damage_player(
             collider,
             QueryFilter::new(),
             |entity| {
-                if let Ok(mut player) = player.get_mut(entity) {
-                    player.health -= enemy.damage_per_second * time.delta_seconds();
+                if let Ok((player_transform, mut player, mut invulnerability, dash)) =
+                    player.get_mut(entity)
+                {
+                    if invulnerability.active() || dash.is_dashing() {
+                        return true;
+                    }
+                    //Armor is flat reduction but a hit always hurts a little
+                    let damage = (enemy.damage - player.armor).max(1.0);
+                    player.health -= damage;
+                    invulnerability.grant(player.hit_invulnerability);
+
+                    spawn_world_text(
+                        &mut commands,
+                        &assets,
+                        player_transform.translation.truncate(),
+                        &format!("{:?}", damage as i32),
+                        Color::rgb(1.0, 0.55, 0.1),
+                    );
+                    audio.play_with_settings(
+                        assets.load("hurt.wav"),
+                        PlaybackSettings {
+                            repeat: false,
+                            volume: 0.6,
+                            speed: 1.0,
+                        },
+                    );
                 }
                 true
             },
@@ -143,27 +257,32 @@ fn enemy_death_check(
     mut commands: Commands,
     assets: Res<AssetServer>,
     mut enemies: Query<(Entity, &Transform, &Enemy, &mut RngComponent)>,
+    mut player: Query<&mut Player>,
 ) {
     //TODO dying animation
     for (entity, transform, enemy, mut rng) in &mut enemies {
         if enemy.health <= 0.0 {
+            if let Ok(mut player) = player.get_single_mut() {
+                player.kills += 1;
+            }
             //TODO fire event for sounds
             commands.entity(entity).despawn_recursive();
-            //Spawn exp orb (can extract into fn)
-            if rng.f32() > 0.5 {
-                let mut orb = ExpOrbBundle::default();
-                orb.sprite.texture = assets.load("coin_1.png");
-                orb.sprite.transform.translation.x = transform.translation.x;
-                orb.sprite.transform.translation.y = transform.translation.y;
-                commands.spawn((
-                    orb,
-                    TwoFrameAnimation {
-                        frame_1: assets.load("coin_1.png"),
-                        frame_2: assets.load("coin_2.png"),
-                        current_frame: false,
-                        timer: Timer::from_seconds(0.3, TimerMode::Repeating),
-                    },
-                ));
+            if enemy.elite || enemy.boss {
+                spawn_pickup(
+                    &mut commands,
+                    &assets,
+                    Pickup::Chest,
+                    transform.translation.truncate(),
+                );
+            } else if rng.f32() > 0.5 {
+                spawn_exp_orb(&mut commands, &assets, 1, transform.translation.truncate());
+            } else if rng.f32() < 0.02 {
+                spawn_pickup(
+                    &mut commands,
+                    &assets,
+                    Pickup::Magnet,
+                    transform.translation.truncate(),
+                );
             }
         }
     }
@@ -1,10 +1,43 @@
-use crate::prelude::*;
+use crate::{prelude::*, storage::parse_baked};
+
+//Past this many orbs the far away ones get folded together
+const ORB_LIMIT: usize = 300;
+const MERGE_DISTANCE: f32 = 25.0;
+const ORB_POP_SPEED: f32 = 6.0;
 
 pub struct ExpPlugin;
 
 impl Plugin for ExpPlugin {
     fn build(&self, app: &mut App) {
-        app.add_system(orb_move_to_player.in_set(OnUpdate(GameState::Gameplay)));
+        app.add_systems((orb_move_to_player, merge_orbs).in_set(OnUpdate(GameState::Gameplay)))
+            .insert_resource(load_exp_curve());
+    }
+}
+
+fn load_exp_curve() -> ExpCurve {
+    let curve: ExpCurve = parse_baked("exp_curve.ron", include_str!("../assets/exp_curve.ron"));
+    assert!(
+        !curve.segments.is_empty(),
+        "Need at least one exp curve segment"
+    );
+    curve
+}
+
+impl ExpCurve {
+    fn increase_at(&self, level: i64) -> i64 {
+        self.segments
+            .iter()
+            .find(|segment| segment.until_level.is_none_or(|until| level < until))
+            .or(self.segments.last())
+            .map_or(0, |segment| segment.increase)
+    }
+
+    /// Exp needed to go from `level` to the next one
+    pub fn exp_to_next(&self, level: i64) -> i64 {
+        //Never zero or leveling would loop forever
+        (1..level)
+            .fold(self.first_level, |exp, level| exp + self.increase_at(level))
+            .max(1)
     }
 }
 
@@ -21,31 +54,127 @@ impl Default for ExpOrbBundle {
             },
             exp_orb: ExpOrb {
                 value: 1,
-                collection_speed: 5.0,
+                collection_speed: 2.0,
+                acceleration: 30.0,
                 collecting: false,
+                pop: Timer::from_seconds(0.15, TimerMode::Once),
+                velocity: Vec2::ZERO,
             },
             game_play: GamePlayEntity,
-            collider: Collider::ball(1.0),
+            //Small so orbs only count once they actually touch the player
+            collider: Collider::ball(0.2),
             sensor: Sensor,
+            //Orbs have no rigid body and the player is kinematic, without this the narrow phase never pairs them
+            collision_types: ActiveCollisionTypes::default()
+                | ActiveCollisionTypes::KINEMATIC_STATIC,
         }
     }
 }
 
 fn orb_move_to_player(
-    mut orbs: Query<(&mut Transform, &ExpOrb)>,
+    mut orbs: Query<(&mut Transform, &mut ExpOrb)>,
     player: Query<&Transform, (With<Player>, Without<ExpOrb>)>,
     time: Res<Time>,
 ) {
-    let player_transform = player.single();
-    for (mut transform, orb) in &mut orbs {
-        if orb.collecting {
-            //TODO bouncing animation
-            let direction = (transform.translation.truncate()
-                - player_transform.translation.truncate())
-            .normalize();
-
-            transform.translation -=
-                (direction * time.delta_seconds() * orb.collection_speed).extend(0.);
+    let player_position = player.single().translation.truncate();
+    for (mut transform, mut orb) in &mut orbs {
+        if !orb.collecting {
+            continue;
         }
+        let to_player = player_position - transform.translation.truncate();
+
+        if orb.pop.elapsed_secs() == 0.0 {
+            orb.velocity = -to_player.normalize_or_zero() * ORB_POP_SPEED;
+        }
+        orb.pop.tick(time.delta());
+        if !orb.pop.finished() {
+            transform.translation += (orb.velocity * time.delta_seconds()).extend(0.0);
+            continue;
+        }
+
+        //Keeps speeding up so even a dashing player gets caught eventually
+        orb.collection_speed += orb.acceleration * time.delta_seconds();
+        let step = orb.collection_speed * time.delta_seconds();
+        //Don't overshoot and orbit the player
+        if step >= to_player.length() {
+            transform.translation = player_position.extend(transform.translation.z);
+        } else {
+            transform.translation += (to_player.normalize() * step).extend(0.0);
+        }
+    }
+}
+
+//Sprites and size for each value tier
+fn orb_tier(value: i64) -> (&'static str, &'static str, f32) {
+    if value >= 50 {
+        ("orb_red_1.png", "orb_red_2.png", 0.7)
+    } else if value >= 10 {
+        ("orb_blue_1.png", "orb_blue_2.png", 0.55)
+    } else {
+        ("coin_1.png", "coin_2.png", 0.4)
     }
 }
+
+pub fn spawn_exp_orb(commands: &mut Commands, assets: &AssetServer, value: i64, position: Vec2) {
+    let (frame_1, frame_2, size) = orb_tier(value);
+
+    let mut orb = ExpOrbBundle::default();
+    orb.sprite.texture = assets.load(frame_1);
+    orb.sprite.sprite.custom_size = Some(Vec2::splat(size));
+    orb.sprite.transform.translation.x = position.x;
+    orb.sprite.transform.translation.y = position.y;
+    orb.exp_orb.value = value;
+    commands.spawn((
+        orb,
+        TwoFrameAnimation {
+            frame_1: assets.load(frame_1),
+            frame_2: assets.load(frame_2),
+            current_frame: false,
+            timer: Timer::from_seconds(0.3, TimerMode::Repeating),
+        },
+    ));
+}
+
+fn merge_orbs(
+    mut commands: Commands,
+    assets: Res<AssetServer>,
+    orbs: Query<(Entity, &Transform, &ExpOrb)>,
+    player: Query<&Transform, (With<Player>, Without<ExpOrb>)>,
+) {
+    let count = orbs.iter().len();
+    if count <= ORB_LIMIT {
+        return;
+    }
+    let player = player.single().translation.truncate();
+
+    //Anything already flying in is left alone
+    let mut far_orbs: Vec<_> = orbs
+        .iter()
+        .map(|(entity, transform, orb)| (entity, transform.translation.truncate(), orb))
+        .filter(|(_, position, orb)| !orb.collecting && position.distance(player) >= MERGE_DISTANCE)
+        .collect();
+    //A single far orb would just get replaced by itself every frame
+    if far_orbs.len() < 2 {
+        return;
+    }
+
+    //Leave the big one where the player will have to go get it
+    let Some(anchor) = far_orbs
+        .iter()
+        .map(|(_, position, _)| *position)
+        .max_by(|a, b| a.distance(player).total_cmp(&b.distance(player)))
+    else {
+        return;
+    };
+    //Only fold the orbs closest to it, just enough to get back under the limit
+    far_orbs.sort_by(|(_, a, _), (_, b, _)| a.distance(anchor).total_cmp(&b.distance(anchor)));
+    let merged = (count - ORB_LIMIT + 1).min(far_orbs.len());
+
+    let mut value = 0;
+    for (entity, _, orb) in &far_orbs[..merged] {
+        value += orb.value;
+        commands.entity(*entity).despawn_recursive();
+    }
+
+    spawn_exp_orb(&mut commands, &assets, value, anchor);
+}
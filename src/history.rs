@@ -0,0 +1,529 @@
+use std::{
+    fs,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use bevy::ecs::system::SystemParam;
+use serde::Deserialize;
+use strum::IntoEnumIterator;
+
+use crate::{
+    prelude::*,
+    stage::selected_stage,
+    storage::{backup_file, data_file, save_ron},
+};
+
+const HISTORY_FILE: &str = "history.ron";
+//Bump when RunRecord changes in a way serde defaults can't cover and handle it in migrate
+const HISTORY_VERSION: u32 = 2;
+//Oldest runs fall off past this
+const MAX_RUNS: usize = 200;
+const TABLE_ROWS: usize = 10;
+
+pub struct HistoryPlugin;
+
+impl Plugin for HistoryPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(load_history())
+            .init_resource::<HistorySort>()
+            .init_resource::<RunClock>()
+            .add_system(reset_run_clock.in_schedule(OnEnter(GameState::StartingLoop)))
+            .add_system(tick_run_clock.in_set(OnUpdate(GameState::Gameplay)))
+            .add_system(record_run.in_schedule(OnEnter(GameState::GameOver)))
+            .add_system(despawn_history_ui.in_schedule(OnExit(GameState::History)))
+            .add_system(history_button_system)
+            .add_systems(
+                (
+                    show_history_ui,
+                    sort_button_system,
+                    history_back_button_system,
+                )
+                    .in_set(OnUpdate(GameState::History)),
+            );
+    }
+}
+
+impl HistorySort {
+    fn label(&self) -> &str {
+        match self {
+            HistorySort::Survived => "Time",
+            HistorySort::Level => "Level",
+            HistorySort::Kills => "Kills",
+            HistorySort::Gold => "Gold",
+            HistorySort::Recent => "Recent",
+        }
+    }
+
+    //Best first
+    fn sort(&self, runs: &mut [&RunRecord]) {
+        match self {
+            HistorySort::Survived => runs.sort_by(|a, b| b.survived.total_cmp(&a.survived)),
+            HistorySort::Level => runs.sort_by_key(|run| std::cmp::Reverse(run.level)),
+            HistorySort::Kills => runs.sort_by_key(|run| std::cmp::Reverse(run.kills)),
+            HistorySort::Gold => runs.sort_by_key(|run| std::cmp::Reverse(run.gold)),
+            HistorySort::Recent => runs.sort_by_key(|run| std::cmp::Reverse(run.finished_at)),
+        }
+    }
+}
+
+impl Default for RunHistory {
+    fn default() -> Self {
+        RunHistory {
+            version: HISTORY_VERSION,
+            runs: Vec::new(),
+        }
+    }
+}
+
+/// Just enough of the file to pick the right layout for the rest
+#[derive(Deserialize)]
+struct HistoryVersion {
+    #[serde(default)]
+    version: u32,
+}
+
+/// Version 1 kept the build as upgrade values, which broke whenever one was renamed
+#[derive(Deserialize)]
+struct RunHistoryV1 {
+    #[serde(default)]
+    runs: Vec<RunRecordV1>,
+}
+
+#[derive(Default, Deserialize)]
+#[serde(default)]
+struct RunRecordV1 {
+    finished_at: u64,
+    character: String,
+    stage: String,
+    survived: f32,
+    level: i64,
+    kills: u32,
+    gold: u32,
+    build: Vec<(WeaponUpgrade, u32)>,
+}
+
+fn load_history() -> RunHistory {
+    let path = data_file(HISTORY_FILE);
+    let Some(text) = path.as_ref().and_then(|path| fs::read_to_string(path).ok()) else {
+        return RunHistory::default();
+    };
+    match migrate(&text) {
+        Ok(history) => history,
+        Err(err) => {
+            warn!("Failed to parse {}: {}", HISTORY_FILE, err);
+            if let Some(path) = path {
+                backup_file(&path);
+            }
+            RunHistory::default()
+        }
+    }
+}
+
+//Files from before versioning load as version 0 and share version 1's layout
+fn migrate(text: &str) -> Result<RunHistory, ron::error::SpannedError> {
+    let HistoryVersion { version } = ron::from_str(text)?;
+    let mut history = if version < 2 {
+        let old: RunHistoryV1 = ron::from_str(text)?;
+        RunHistory {
+            version,
+            runs: old.runs.into_iter().map(migrate_run_v1).collect(),
+        }
+    } else {
+        ron::from_str(text)?
+    };
+    history.version = HISTORY_VERSION;
+    Ok(history)
+}
+
+fn migrate_run_v1(run: RunRecordV1) -> RunRecord {
+    RunRecord {
+        finished_at: run.finished_at,
+        character: run.character,
+        stage: run.stage,
+        survived: run.survived,
+        level: run.level,
+        kills: run.kills,
+        gold: run.gold,
+        build: run
+            .build
+            .iter()
+            .map(|(upgrade, level)| build_name(upgrade, *level))
+            .collect(),
+    }
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |time| time.as_secs())
+}
+
+//Year-month-day from days since the epoch (Howard Hinnant's civil_from_days)
+fn format_date(timestamp: u64) -> String {
+    let z = (timestamp / 86_400) as i64 + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+    format!("{:04}-{:02}-{:02}", year, month, day)
+}
+
+fn format_time(seconds: f32) -> String {
+    let seconds = seconds as u32;
+    format!("{}:{:02}", seconds / 60, seconds % 60)
+}
+
+fn build_name(upgrade: &WeaponUpgrade, level: u32) -> String {
+    if upgrade.is_evolution() {
+        upgrade.name().to_string()
+    } else {
+        format!("{} {}", upgrade.name(), level)
+    }
+}
+
+fn build_names(inventory: &Inventory) -> Vec<String> {
+    inventory
+        .levels
+        .iter()
+        .map(|(upgrade, level)| build_name(upgrade, *level))
+        .collect()
+}
+
+fn reset_run_clock(mut run_clock: ResMut<RunClock>) {
+    run_clock.0.reset();
+}
+
+fn tick_run_clock(mut run_clock: ResMut<RunClock>, time: Res<Time>) {
+    run_clock.0.tick(time.delta());
+}
+
+/// Character and stage the run was played with
+#[derive(SystemParam)]
+struct RunSelection<'w> {
+    characters: Res<'w, Characters>,
+    selected_character: Res<'w, SelectedCharacter>,
+    stages: Res<'w, Stages>,
+    selected_stage: Res<'w, SelectedStage>,
+}
+
+impl RunSelection<'_> {
+    fn character_name(&self) -> &str {
+        let characters = &self.characters.0;
+        &characters
+            .get(self.selected_character.0)
+            .unwrap_or(&characters[0])
+            .name
+    }
+
+    fn stage_name(&self) -> &str {
+        &selected_stage(&self.stages, &self.selected_stage).name
+    }
+}
+
+fn record_run(
+    mut history: ResMut<RunHistory>,
+    player: Query<&Player>,
+    inventory: Res<Inventory>,
+    run_clock: Res<RunClock>,
+    selection: RunSelection,
+) {
+    let Ok(player) = player.get_single() else {
+        return;
+    };
+
+    history.runs.push(RunRecord {
+        finished_at: now(),
+        character: selection.character_name().to_string(),
+        stage: selection.stage_name().to_string(),
+        survived: run_clock.0.elapsed_secs(),
+        level: player.level,
+        kills: player.kills,
+        gold: player.gold,
+        build: build_names(&inventory),
+    });
+    if history.runs.len() > MAX_RUNS {
+        let extra = history.runs.len() - MAX_RUNS;
+        history.runs.drain(..extra);
+    }
+    save_ron(data_file(HISTORY_FILE), &*history);
+}
+
+fn history_button_system(
+    mut interaction_query: Query<
+        (&Interaction, &mut BackgroundColor),
+        (Changed<Interaction>, With<Button>, With<HistoryButtonUI>),
+    >,
+    about: Res<AboutShown>,
+    controls: Res<ControlsShown>,
+    mut next_state: ResMut<NextState<GameState>>,
+) {
+    if about.0 || controls.0 {
+        return;
+    }
+    for (interaction, mut color) in &mut interaction_query {
+        match *interaction {
+            Interaction::Clicked => {
+                *color = Color::RED.into();
+                next_state.set(GameState::History);
+            }
+            Interaction::Hovered => {
+                *color = Color::GREEN.into();
+            }
+            Interaction::None => {
+                *color = Color::CRIMSON.into();
+            }
+        }
+    }
+}
+
+fn sort_button_system(
+    mut interaction_query: Query<
+        (&Interaction, &mut BackgroundColor, &HistorySortButtonUI),
+        (Changed<Interaction>, With<Button>),
+    >,
+    mut sort: ResMut<HistorySort>,
+) {
+    for (interaction, mut color, button) in &mut interaction_query {
+        match *interaction {
+            Interaction::Clicked => {
+                *color = Color::RED.into();
+                if *sort != button.0 {
+                    *sort = button.0;
+                }
+            }
+            Interaction::Hovered => {
+                *color = Color::GREEN.into();
+            }
+            Interaction::None => {
+                *color = sort_button_color(*sort == button.0).into();
+            }
+        }
+    }
+}
+
+fn sort_button_color(selected: bool) -> Color {
+    if selected {
+        Color::DARK_GREEN
+    } else {
+        Color::CRIMSON
+    }
+}
+
+fn history_back_button_system(
+    mut interaction_query: Query<
+        (&Interaction, &mut BackgroundColor),
+        (
+            Changed<Interaction>,
+            With<Button>,
+            With<HistoryBackButtonUI>,
+        ),
+    >,
+    mut next_state: ResMut<NextState<GameState>>,
+) {
+    for (interaction, mut color) in &mut interaction_query {
+        match *interaction {
+            Interaction::Clicked => {
+                *color = Color::RED.into();
+                next_state.set(GameState::MainMenu);
+            }
+            Interaction::Hovered => {
+                *color = Color::GREEN.into();
+            }
+            Interaction::None => {
+                *color = Color::CRIMSON.into();
+            }
+        }
+    }
+}
+
+//Rebuilt whenever the sort column changes
+fn show_history_ui(
+    mut commands: Commands,
+    assets: Res<AssetServer>,
+    history: Res<RunHistory>,
+    sort: Res<HistorySort>,
+    ui: Query<Entity, With<HistoryUI>>,
+) {
+    if !sort.is_changed() && !ui.is_empty() {
+        return;
+    }
+    for ui in &ui {
+        commands.entity(ui).despawn_recursive();
+    }
+    spawn_history_ui(&mut commands, &assets, &history, *sort);
+}
+
+fn spawn_history_ui(
+    commands: &mut Commands,
+    assets: &AssetServer,
+    history: &RunHistory,
+    sort: HistorySort,
+) {
+    let font = assets.load("fonts/pointfree.ttf");
+    let text_style = |font_size: f32| TextStyle {
+        font: font.clone(),
+        font_size,
+        color: Color::rgb(0.9, 0.9, 0.9),
+    };
+
+    let history_parent = (
+        NodeBundle {
+            style: Style {
+                size: Size::new(Val::Percent(100.0), Val::Percent(100.0)),
+                position_type: PositionType::Absolute,
+                align_items: AlignItems::Center,
+                justify_content: JustifyContent::Center,
+                ..default()
+            },
+            ..default()
+        },
+        HistoryUI,
+        Name::new("History UI"),
+    );
+
+    let history_box = NodeBundle {
+        style: Style {
+            size: Size::new(Val::Percent(90.0), Val::Percent(92.0)),
+            position_type: PositionType::Relative,
+            align_items: AlignItems::Center,
+            flex_direction: FlexDirection::Column,
+            justify_content: JustifyContent::SpaceAround,
+            ..default()
+        },
+        background_color: Color::DARK_GRAY.into(),
+        ..default()
+    };
+
+    let row = |height: f32| NodeBundle {
+        style: Style {
+            size: Size::new(Val::Percent(95.0), Val::Percent(height)),
+            flex_direction: FlexDirection::Row,
+            justify_content: JustifyContent::SpaceBetween,
+            align_items: AlignItems::Center,
+            ..default()
+        },
+        ..default()
+    };
+
+    let cell = |width: f32| NodeBundle {
+        style: Style {
+            size: Size::new(Val::Percent(width), Val::Percent(100.0)),
+            align_items: AlignItems::Center,
+            ..default()
+        },
+        ..default()
+    };
+
+    let button = |width: f32, color: Color| ButtonBundle {
+        style: Style {
+            size: Size::new(Val::Percent(width), Val::Percent(100.0)),
+            justify_content: JustifyContent::Center,
+            align_items: AlignItems::Center,
+            ..default()
+        },
+        background_color: color.into(),
+        ..default()
+    };
+
+    let mut runs = history.runs.iter().collect::<Vec<_>>();
+    sort.sort(&mut runs);
+
+    //Date, character, stage, time, level, kills, gold, build
+    let widths = [11.0, 12.0, 12.0, 6.0, 5.0, 6.0, 6.0, 40.0];
+    let headers = [
+        "Date",
+        "Character",
+        "Stage",
+        "Time",
+        "Lv",
+        "Kills",
+        "Gold",
+        "Build",
+    ];
+
+    commands.spawn(history_parent).with_children(|commands| {
+        commands.spawn(history_box).with_children(|commands| {
+            commands.spawn(TextBundle::from_section("High Scores", text_style(48.0)));
+
+            commands.spawn(row(7.0)).with_children(|commands| {
+                commands.spawn(TextBundle::from_section("Sort by", text_style(24.0)));
+                for column in HistorySort::iter() {
+                    commands
+                        .spawn((
+                            button(15.0, sort_button_color(column == sort)),
+                            HistorySortButtonUI(column),
+                        ))
+                        .with_children(|commands| {
+                            commands
+                                .spawn(TextBundle::from_section(column.label(), text_style(24.0)));
+                        });
+                }
+            });
+
+            commands.spawn(row(5.0)).with_children(|commands| {
+                for (header, width) in headers.iter().zip(widths) {
+                    commands.spawn(cell(width)).with_children(|commands| {
+                        commands.spawn(TextBundle::from_section(
+                            *header,
+                            TextStyle {
+                                color: Color::GOLD,
+                                ..text_style(20.0)
+                            },
+                        ));
+                    });
+                }
+            });
+
+            if runs.is_empty() {
+                commands.spawn(TextBundle::from_section(
+                    "No runs yet, go deliver some pizza!",
+                    text_style(28.0),
+                ));
+            }
+
+            for run in runs.iter().take(TABLE_ROWS) {
+                let columns = [
+                    format_date(run.finished_at),
+                    run.character.clone(),
+                    run.stage.clone(),
+                    format_time(run.survived),
+                    run.level.to_string(),
+                    run.kills.to_string(),
+                    run.gold.to_string(),
+                    run.build.join(", "),
+                ];
+                commands.spawn(row(6.0)).with_children(|commands| {
+                    for (i, (value, width)) in columns.into_iter().zip(widths).enumerate() {
+                        //The build list is long, keep it smaller
+                        let font_size = if i == widths.len() - 1 { 14.0 } else { 20.0 };
+                        commands.spawn(cell(width)).with_children(|commands| {
+                            commands.spawn(TextBundle::from_section(value, text_style(font_size)));
+                        });
+                    }
+                });
+            }
+
+            commands.spawn(row(8.0)).with_children(|commands| {
+                commands.spawn(TextBundle::from_section(
+                    format!("{} runs played", history.runs.len()),
+                    text_style(24.0),
+                ));
+                commands
+                    .spawn((button(30.0, Color::CRIMSON), HistoryBackButtonUI))
+                    .with_children(|commands| {
+                        commands.spawn(TextBundle::from_section("Back", text_style(28.0)));
+                    });
+            });
+        });
+    });
+}
+
+fn despawn_history_ui(mut commands: Commands, ui: Query<Entity, With<HistoryUI>>) {
+    for ui in &ui {
+        commands.entity(ui).despawn_recursive();
+    }
+}
@@ -0,0 +1,231 @@
+use bevy::input::InputSystem;
+
+use crate::{
+    prelude::*,
+    storage::{backup_file, config_file, load_ron, save_ron},
+};
+
+const BINDINGS_FILE: &str = "controls.ron";
+const GAMEPAD_DEADZONE: f32 = 0.2;
+//How far a stick has to be pushed to count when rebinding
+const REBIND_AXIS_THRESHOLD: f32 = 0.5;
+const STICK_AXES: [GamepadAxisType; 4] = [
+    GamepadAxisType::LeftStickX,
+    GamepadAxisType::LeftStickY,
+    GamepadAxisType::RightStickX,
+    GamepadAxisType::RightStickY,
+];
+
+pub struct GameInputPlugin;
+
+impl Plugin for GameInputPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(load_bindings())
+            .init_resource::<ActionState>()
+            .init_resource::<RebindingAction>()
+            .add_system(
+                update_action_state
+                    .in_base_set(CoreSet::PreUpdate)
+                    .after(InputSystem),
+            )
+            .add_system(listen_for_rebind);
+    }
+}
+
+impl Default for InputBindings {
+    fn default() -> Self {
+        use BoundInput::*;
+        use InputAction::*;
+
+        let bind = |action, input, scale| Binding {
+            action,
+            input,
+            scale,
+        };
+
+        Self {
+            bindings: vec![
+                bind(MoveX, Key(KeyCode::D), 1.0),
+                bind(MoveX, Key(KeyCode::A), -1.0),
+                bind(MoveX, Key(KeyCode::Right), 1.0),
+                bind(MoveX, Key(KeyCode::Left), -1.0),
+                bind(MoveX, GamepadAxis(GamepadAxisType::LeftStickX), 1.0),
+                bind(MoveX, GamepadButton(GamepadButtonType::DPadRight), 1.0),
+                bind(MoveX, GamepadButton(GamepadButtonType::DPadLeft), -1.0),
+                bind(MoveY, Key(KeyCode::W), 1.0),
+                bind(MoveY, Key(KeyCode::S), -1.0),
+                bind(MoveY, Key(KeyCode::Up), 1.0),
+                bind(MoveY, Key(KeyCode::Down), -1.0),
+                bind(MoveY, GamepadAxis(GamepadAxisType::LeftStickY), 1.0),
+                bind(MoveY, GamepadButton(GamepadButtonType::DPadUp), 1.0),
+                bind(MoveY, GamepadButton(GamepadButtonType::DPadDown), -1.0),
+                bind(Confirm, Key(KeyCode::Return), 1.0),
+                bind(Confirm, GamepadButton(GamepadButtonType::South), 1.0),
+                bind(Pause, Key(KeyCode::Escape), 1.0),
+                bind(Pause, GamepadButton(GamepadButtonType::Start), 1.0),
+                bind(Dash, Key(KeyCode::Space), 1.0),
+                bind(Dash, GamepadButton(GamepadButtonType::East), 1.0),
+            ],
+        }
+    }
+}
+
+impl Binding {
+    pub fn action_name(&self) -> &str {
+        match (self.action, self.input, self.scale > 0.0) {
+            //Sticks cover both directions of the axis
+            (InputAction::MoveX, BoundInput::GamepadAxis(_), _) => "Move X",
+            (InputAction::MoveY, BoundInput::GamepadAxis(_), _) => "Move Y",
+            (InputAction::MoveX, _, true) => "Right",
+            (InputAction::MoveX, _, false) => "Left",
+            (InputAction::MoveY, _, true) => "Up",
+            (InputAction::MoveY, _, false) => "Down",
+            (InputAction::Confirm, _, _) => "Confirm",
+            (InputAction::Pause, _, _) => "Pause",
+            (InputAction::Dash, _, _) => "Dash",
+        }
+    }
+}
+
+impl BoundInput {
+    pub fn name(&self) -> String {
+        match self {
+            BoundInput::Key(key) => format!("{:?}", key),
+            BoundInput::GamepadButton(button) => format!("Pad {:?}", button),
+            BoundInput::GamepadAxis(axis) => format!("Pad {:?}", axis),
+        }
+    }
+}
+
+impl ActionState {
+    pub fn value(&self, action: InputAction) -> f32 {
+        self.values.get(&action).copied().unwrap_or(0.0)
+    }
+
+    pub fn pressed(&self, action: InputAction) -> bool {
+        self.value(action).abs() > 0.5
+    }
+
+    pub fn just_pressed(&self, action: InputAction) -> bool {
+        let previous = self.previous.get(&action).copied().unwrap_or(0.0);
+        self.pressed(action) && previous.abs() <= 0.5
+    }
+
+    pub fn movement(&self) -> Vec2 {
+        Vec2::new(
+            self.value(InputAction::MoveX),
+            self.value(InputAction::MoveY),
+        )
+    }
+}
+
+fn load_bindings() -> InputBindings {
+    let path = config_file(BINDINGS_FILE);
+    let exists = path.as_ref().is_some_and(|path| path.exists());
+    if let Some(bindings) = load_ron(path.clone()) {
+        return bindings;
+    }
+
+    //A hand edit gone wrong, move it aside so the player can fix it up
+    if let (true, Some(path)) = (exists, &path) {
+        backup_file(path);
+    }
+    //Write out the defaults so there is something to edit
+    let bindings = InputBindings::default();
+    save_ron(path, &bindings);
+    bindings
+}
+
+pub fn save_bindings(bindings: &InputBindings) {
+    save_ron(config_file(BINDINGS_FILE), bindings);
+}
+
+fn update_action_state(
+    mut state: ResMut<ActionState>,
+    bindings: Res<InputBindings>,
+    keyboard: Res<Input<KeyCode>>,
+    gamepads: Res<Gamepads>,
+    gamepad_buttons: Res<Input<GamepadButton>>,
+    gamepad_axes: Res<Axis<GamepadAxis>>,
+) {
+    let state = &mut *state;
+    state.previous = std::mem::take(&mut state.values);
+
+    for binding in &bindings.bindings {
+        let value = match binding.input {
+            BoundInput::Key(key) => keyboard.pressed(key) as i32 as f32,
+            BoundInput::GamepadButton(button) => gamepads
+                .iter()
+                .any(|gamepad| gamepad_buttons.pressed(GamepadButton::new(gamepad, button)))
+                as i32 as f32,
+            BoundInput::GamepadAxis(axis) => gamepads
+                .iter()
+                .filter_map(|gamepad| gamepad_axes.get(GamepadAxis::new(gamepad, axis)))
+                .find(|value| value.abs() > GAMEPAD_DEADZONE)
+                .unwrap_or(0.0),
+        };
+        *state.values.entry(binding.action).or_default() += value * binding.scale;
+    }
+
+    for value in state.values.values_mut() {
+        *value = value.clamp(-1.0, 1.0);
+    }
+}
+
+fn listen_for_rebind(
+    mut rebinding: ResMut<RebindingAction>,
+    mut bindings: ResMut<InputBindings>,
+    keyboard: Res<Input<KeyCode>>,
+    gamepads: Res<Gamepads>,
+    gamepad_buttons: Res<Input<GamepadButton>>,
+    gamepad_axes: Res<Axis<GamepadAxis>>,
+) {
+    let Some(index) = rebinding.0 else {
+        return;
+    };
+    //Started this frame, possibly by a confirm key that is still just pressed
+    if rebinding.is_changed() {
+        return;
+    }
+    let Some(old) = bindings.bindings.get(index).copied() else {
+        rebinding.0 = None;
+        return;
+    };
+
+    if keyboard.just_pressed(KeyCode::Escape) {
+        rebinding.0 = None;
+        return;
+    }
+
+    //A stick row covers both directions so only another stick can replace it,
+    //a key or button only has one direction and keeps the sign of the row it replaces
+    let new_input = if matches!(old.input, BoundInput::GamepadAxis(_)) {
+        STICK_AXES
+            .into_iter()
+            .find(|axis| {
+                gamepads.iter().any(|gamepad| {
+                    gamepad_axes
+                        .get(GamepadAxis::new(gamepad, *axis))
+                        .is_some_and(|value| value.abs() > REBIND_AXIS_THRESHOLD)
+                })
+            })
+            .map(BoundInput::GamepadAxis)
+    } else if let Some(key) = keyboard.get_just_pressed().next() {
+        Some(BoundInput::Key(*key))
+    } else {
+        gamepad_buttons
+            .get_just_pressed()
+            .next()
+            .map(|button| BoundInput::GamepadButton(button.button_type))
+    };
+
+    if let Some(input) = new_input {
+        bindings.bindings[index] = Binding {
+            input,
+            scale: old.scale.signum(),
+            ..old
+        };
+        save_bindings(&bindings);
+        rebinding.0 = None;
+    }
+}
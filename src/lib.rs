@@ -1,9 +1,24 @@
+//Bevy queries with filters trip this on nearly every UI system
+#![allow(clippy::type_complexity)]
+
 mod animation;
 mod attack;
 mod camera;
+mod character;
+mod chest;
 mod enemy;
 mod exp;
+mod history;
+mod input;
+mod menu;
+mod pause;
 mod player;
+mod prop;
+mod save;
+mod shop;
+mod stage;
+mod storage;
+mod targeting;
 mod ui;
 mod upgrade;
 
@@ -17,43 +32,140 @@ pub mod prelude {
     pub const RENDER_HEIGHT: f32 = 1080.;
     pub const PIXEL_TO_WORLD: f32 = 30. / 1080.;
 
+    //Arena walls only stop the player, enemies spawning outside can still walk in
+    pub const PLAYER_GROUP: Group = Group::GROUP_1;
+    pub const WALL_GROUP: Group = Group::GROUP_2;
+    pub const ENEMY_GROUP: Group = Group::GROUP_3;
+
     pub use crate::animation::GameAnimationPlugin;
     pub use crate::attack::AttackPlugin;
     pub use crate::camera::GameCameraPlugin;
+    pub use crate::character::CharacterPlugin;
+    pub use crate::chest::ChestPlugin;
     pub use crate::enemy::EnemyPlugin;
     pub use crate::exp::ExpPlugin;
+    pub use crate::history::HistoryPlugin;
+    pub use crate::input::GameInputPlugin;
+    pub use crate::menu::MenuPlugin;
+    pub use crate::pause::PausePlugin;
     pub use crate::player::PlayerPlugin;
+    pub use crate::prop::PropPlugin;
+    pub use crate::save::SavePlugin;
+    pub use crate::shop::ShopPlugin;
+    pub use crate::stage::StagePlugin;
     pub use crate::ui::GameUiPlugin;
     pub use crate::upgrade::UpgradePlugin;
 
     pub use bevy::prelude::*;
-    use bevy::time::Stopwatch;
+    use bevy::{time::Stopwatch, utils::HashMap};
     pub use bevy_rapier2d::prelude::*;
     pub use bevy_turborand::prelude::*;
+    use serde::{Deserialize, Serialize};
 
     #[derive(States, PartialEq, Eq, Default, Debug, Clone, Hash)]
     pub enum GameState {
         #[default]
         MainMenu,
+        StageSelect,
+        CharacterSelect,
+        Shop,
+        History,
         StartingLoop,
         Gameplay,
         LevelUp,
+        Chest,
+        Paused,
         GameOver,
     }
 
-    #[derive(Component, Clone)]
+    #[derive(Component, Clone, Serialize, Deserialize)]
     pub struct Enemy {
         pub speed: f32,
         pub health: f32,
         pub asset: String,
-        pub damage_per_second: f32,
+        pub damage: f32,
+        //Tougher and drops a chest
+        #[serde(default)]
+        pub elite: bool,
+        //Rare giant that also drops a chest
+        #[serde(default)]
+        pub boss: bool,
     }
 
     #[derive(Resource, Default)]
     pub struct CursorPosition {
         pub screen_position: Vec2,
+        pub world_position: Vec2,
+    }
+
+    #[derive(Resource, Default, Clone, Copy, PartialEq, Eq, Debug)]
+    pub enum AimMode {
+        #[default]
+        Auto,
+        Manual,
+    }
+
+    impl AimMode {
+        pub fn name(&self) -> &str {
+            match self {
+                AimMode::Auto => "Aim: Auto",
+                AimMode::Manual => "Aim: Mouse",
+            }
+        }
     }
 
+    #[derive(Component)]
+    pub struct Crosshair;
+
+    #[derive(Clone, Copy, PartialEq, Eq, Hash, Debug, Serialize, Deserialize)]
+    pub enum InputAction {
+        MoveX,
+        MoveY,
+        Confirm,
+        Pause,
+        Dash,
+    }
+
+    #[derive(Clone, Copy, PartialEq, Debug, Serialize, Deserialize)]
+    pub enum BoundInput {
+        Key(KeyCode),
+        GamepadButton(GamepadButtonType),
+        GamepadAxis(GamepadAxisType),
+    }
+
+    #[derive(Clone, Copy, PartialEq, Debug, Serialize, Deserialize)]
+    pub struct Binding {
+        pub action: InputAction,
+        pub input: BoundInput,
+        pub scale: f32,
+    }
+
+    #[derive(Resource, Clone, Serialize, Deserialize)]
+    pub struct InputBindings {
+        pub bindings: Vec<Binding>,
+    }
+
+    #[derive(Resource, Default)]
+    pub struct ActionState {
+        pub values: HashMap<InputAction, f32>,
+        pub previous: HashMap<InputAction, f32>,
+    }
+
+    /// Button highlighted by keyboard or gamepad navigation
+    #[derive(Resource)]
+    pub struct MenuFocus {
+        pub focused: Option<Entity>,
+        //Clicked by confirm last frame, released like a mouse button would be
+        pub pressed: Option<Entity>,
+        //Whether the focus came from keys rather than the mouse
+        pub keyboard: bool,
+        pub repeat: Timer,
+    }
+
+    /// Index into `InputBindings` waiting for a new key
+    #[derive(Resource, Default)]
+    pub struct RebindingAction(pub Option<usize>);
+
     #[derive(Bundle)]
     pub struct ExpOrbBundle {
         #[bundle]
@@ -62,13 +174,32 @@ pub mod prelude {
         pub collider: Collider,
         pub game_play: GamePlayEntity,
         pub sensor: Sensor,
+        pub collision_types: ActiveCollisionTypes,
     }
 
     #[derive(Component)]
     pub struct ExpOrb {
         pub value: i64,
         pub collection_speed: f32,
+        pub acceleration: f32,
         pub collecting: bool,
+        //Short hop away from the player before flying in
+        pub pop: Timer,
+        pub velocity: Vec2,
+    }
+
+    #[derive(Clone, Serialize, Deserialize)]
+    pub struct ExpCurveSegment {
+        //None keeps going forever
+        pub until_level: Option<i64>,
+        pub increase: i64,
+    }
+
+    /// Exp needed for each level, grows by the increase of whichever segment the level falls in
+    #[derive(Resource, Clone, Serialize, Deserialize)]
+    pub struct ExpCurve {
+        pub first_level: i64,
+        pub segments: Vec<ExpCurveSegment>,
     }
 
     #[derive(Component)]
@@ -92,16 +223,153 @@ pub mod prelude {
     pub struct LevelUpUI;
 
     #[derive(Component)]
+    pub struct RerollButtonUI;
+
+    #[derive(Component)]
+    pub struct SkipButtonUI;
+
+    #[derive(Component)]
+    pub struct BanishButtonUI;
+
+    #[derive(Component, Clone, Serialize, Deserialize)]
     pub struct Player {
         pub exp: i64,
         pub next_level_exp: i64,
         pub level: i64,
+        pub pending_levels: i64,
+        //Chests touched but not opened yet, same queue as the levels
+        #[serde(default)]
+        pub pending_chests: u32,
         pub speed: f32,
+        pub velocity: Vec2,
+        pub acceleration: f32,
+        pub deceleration: f32,
         pub health: f32,
         pub max_health: f32,
+        pub armor: f32,
+        pub regen: f32,
+        pub lifesteal: f32,
+        pub magnet: f32,
+        pub revives: u32,
+        pub gold: u32,
+        #[serde(default)]
+        pub kills: u32,
+        pub luck: f32,
+        //Multiplies all weapon damage
+        pub might: f32,
+        pub hit_invulnerability: f32,
+        pub passive: CharacterPassive,
         pub facing: Facing,
     }
 
+    #[derive(Clone, Copy, Serialize, Deserialize)]
+    pub enum CharacterPassive {
+        HealthPerLevel(f32),
+        SpeedPerLevel(f32),
+        ArmorPerLevel(f32),
+        RegenPerLevel(f32),
+    }
+
+    #[derive(Clone, Serialize, Deserialize)]
+    pub struct Character {
+        pub name: String,
+        pub description: String,
+        pub sprite_1: String,
+        pub sprite_2: String,
+        pub speed: f32,
+        pub health: f32,
+        pub armor: f32,
+        pub regen: f32,
+        pub starting_weapon: WeaponUpgrade,
+        pub passive: CharacterPassive,
+    }
+
+    #[derive(Resource)]
+    pub struct Characters(pub Vec<Character>);
+
+    /// Index into `Characters` used by the next run
+    #[derive(Resource, Default)]
+    pub struct SelectedCharacter(pub usize);
+
+    /// Sensor child of the player, orbs inside it start flying in
+    #[derive(Component)]
+    pub struct PickupRadius;
+
+    #[derive(Component, Clone, Copy, Serialize, Deserialize)]
+    pub enum Pickup {
+        //Heals this much
+        Health(f32),
+        Magnet,
+        Gold(u32),
+        //Damage to every enemy near the player
+        Bomb(f32),
+        Chest,
+    }
+
+    #[derive(Clone, Copy, Serialize, Deserialize)]
+    pub struct LootEntry {
+        pub pickup: Pickup,
+        pub weight: f32,
+    }
+
+    #[derive(Clone, Serialize, Deserialize)]
+    pub struct StageProp {
+        pub position: Vec2,
+        pub sprite: String,
+        pub size: Vec2,
+        pub health: f32,
+    }
+
+    /// Which stage prop this is, the chunk is only set on endless stages
+    #[derive(Clone, Copy, PartialEq, Eq, Hash, Debug, Serialize, Deserialize)]
+    pub struct PropId {
+        pub chunk: Option<IVec2>,
+        pub index: usize,
+    }
+
+    /// Run clock seconds each prop broke at, outlives the prop so reloading a chunk can't refill it
+    #[derive(Resource, Default)]
+    pub struct BrokenProps(pub HashMap<PropId, f32>);
+
+    #[derive(Component)]
+    pub struct Prop {
+        pub id: PropId,
+        pub health: f32,
+        pub max_health: f32,
+        //Broken props wait on this before coming back, props without one stay broken
+        pub respawn: Option<Timer>,
+        pub broken: bool,
+    }
+
+    #[derive(Component, Clone, Serialize, Deserialize)]
+    pub struct Dash {
+        pub charges: u32,
+        pub max_charges: u32,
+        pub recharge: Timer,
+        pub active: Timer,
+        pub afterimage: Timer,
+        pub direction: Vec2,
+        pub speed: f32,
+        pub duration: f32,
+    }
+
+    #[derive(Component)]
+    pub struct Afterimage {
+        pub lifetime: Timer,
+    }
+
+    #[derive(Component)]
+    pub struct ReviveRing {
+        pub lifetime: Timer,
+    }
+
+    #[derive(Component)]
+    pub struct Invulnerability {
+        pub timer: Timer,
+        pub flash: Timer,
+    }
+
+    #[derive(Clone, Serialize, Deserialize)]
     pub enum Facing {
         Left,
         Right,
@@ -113,9 +381,18 @@ pub mod prelude {
         pub damage: f32,
     }
 
+    #[derive(Clone, Copy, PartialEq, Eq, Debug)]
+    pub enum TargetingStrategy {
+        Nearest,
+        RandomOnScreen,
+        HighestHealth,
+        DensestCluster,
+    }
+
     #[derive(Component)]
     pub struct CloseShot {
         pub timer: Timer,
+        pub targeting: TargetingStrategy,
     }
 
     #[derive(Component)]
@@ -129,6 +406,7 @@ pub mod prelude {
     #[derive(Component)]
     pub struct AreaShot {
         pub timer: Timer,
+        pub targeting: TargetingStrategy,
     }
 
     #[derive(Component)]
@@ -138,18 +416,86 @@ pub mod prelude {
         pub damage_per_second: f32,
     }
 
-    #[derive(Component, Clone, PartialEq, Eq, EnumIter)]
+    #[derive(Component, Clone, PartialEq, Eq, EnumIter, Serialize, Deserialize)]
     pub enum WeaponUpgrade {
         Whip,
         CloseShot,
         AreaShot,
         HealthUp,
         SpeedUp,
+        ArmorUp,
+        RegenUp,
+        LifestealUp,
+        MagnetUp,
+        Revive,
+        LuckUp,
+        DashCooldown,
+        DashCharge,
+        //Only offered once nothing else is left
+        Heal,
+        Gold,
+        //Only found in chests once the weapon is maxed
+        SpicyRamen,
+        BurritoBarrage,
+        NachoAvalanche,
     }
 
     #[derive(PartialEq, Eq)]
     pub struct UpgradeSelected(pub WeaponUpgrade);
 
+    /// What taking one level of an upgrade does, shared by the apply systems and level up cards
+    #[derive(Clone, Copy, PartialEq, Debug)]
+    pub enum UpgradeEffect {
+        AddEmitter,
+        DamagePercent(f32),
+        MaxHealthPercent(f32),
+        SpeedPercent(f32),
+        Armor(f32),
+        Regen(f32),
+        Lifesteal(f32),
+        MagnetPercent(f32),
+        Revive,
+        Luck(f32),
+        DashCooldownPercent(f32),
+        DashCharge,
+        Heal(f32),
+        Gold(u32),
+    }
+
+    /// Owned upgrades and their levels for the current run
+    #[derive(Resource, Clone, Serialize, Deserialize)]
+    pub struct Inventory {
+        pub weapon_slots: usize,
+        pub passive_slots: usize,
+        pub levels: Vec<(WeaponUpgrade, u32)>,
+    }
+
+    #[derive(Resource, Clone, Serialize, Deserialize)]
+    pub struct LevelUpOffers {
+        //Luck can add one more on top of this
+        pub choices: usize,
+        pub current: Vec<WeaponUpgrade>,
+        pub rerolls: u32,
+        pub skips: u32,
+        pub banishes: u32,
+        //Never offered again this run
+        pub banished: Vec<WeaponUpgrade>,
+        //Next clicked offer gets banished instead of picked
+        pub banishing: bool,
+    }
+
+    pub struct DamageDealt(pub f32);
+
+    /// Health bar state for flashing green when the player heals
+    #[derive(Resource, Default)]
+    pub struct HealthFlash {
+        pub last_health: f32,
+        //Healing not shown yet
+        pub healed: f32,
+        //How long the bar keeps showing it
+        pub flash: f32,
+    }
+
     impl WeaponUpgrade {
         pub fn name(&self) -> &str {
             match self {
@@ -158,10 +504,184 @@ pub mod prelude {
                 WeaponUpgrade::AreaShot => "Nacho Cheese",
                 WeaponUpgrade::HealthUp => "Health Up 10%",
                 WeaponUpgrade::SpeedUp => "Speed Up 10%",
+                WeaponUpgrade::ArmorUp => "Armor +1",
+                WeaponUpgrade::RegenUp => "Regen +0.5/s",
+                WeaponUpgrade::LifestealUp => "Lifesteal +3%",
+                WeaponUpgrade::MagnetUp => "Magnet +25%",
+                WeaponUpgrade::Revive => "Extra Life",
+                WeaponUpgrade::LuckUp => "Luck +10%",
+                WeaponUpgrade::DashCooldown => "Dash Cooldown -15%",
+                WeaponUpgrade::DashCharge => "Dash +1 Charge",
+                WeaponUpgrade::Heal => "Pizza Break: Heal 30",
+                WeaponUpgrade::Gold => "Tip Jar: +25 Gold",
+                WeaponUpgrade::SpicyRamen => "Spicy Ramen",
+                WeaponUpgrade::BurritoBarrage => "Burrito Barrage",
+                WeaponUpgrade::NachoAvalanche => "Nacho Avalanche",
             }
         }
     }
 
+    /// Upgrades won from the chest being opened
+    #[derive(Resource)]
+    pub struct ChestReward {
+        pub rewards: Vec<WeaponUpgrade>,
+        pub spin: Stopwatch,
+        //Next time the spinning slots change names
+        pub tick: Timer,
+    }
+
+    #[derive(Component)]
+    pub struct ChestUI;
+
+    #[derive(Component)]
+    pub struct ChestSlotUI(pub usize);
+
+    #[derive(Component)]
+    pub struct ChestCollectButtonUI;
+
+    /// Permanent power-ups bought with gold between runs
+    #[derive(Clone, Copy, PartialEq, Eq, Debug, EnumIter, Serialize, Deserialize)]
+    pub enum MetaUpgrade {
+        MaxHealth,
+        Might,
+        Magnet,
+        Armor,
+        Revival,
+    }
+
+    /// Saved between runs
+    #[derive(Resource, Default, Serialize, Deserialize)]
+    pub struct Profile {
+        pub gold: u32,
+        #[serde(default)]
+        pub purchased: Vec<(MetaUpgrade, u32)>,
+    }
+
+    #[derive(Component)]
+    pub struct ShopUI;
+
+    #[derive(Component)]
+    pub struct ShopButtonUI;
+
+    #[derive(Component)]
+    pub struct ShopBuyButtonUI(pub MetaUpgrade);
+
+    #[derive(Component)]
+    pub struct ShopRefundButtonUI;
+
+    #[derive(Component)]
+    pub struct ShopBackButtonUI;
+
+    #[derive(Serialize, Deserialize)]
+    pub enum WeaponSnapshot {
+        Whip {
+            damage: f32,
+            translation: Vec3,
+            flip_x: bool,
+            elapsed: f32,
+        },
+        CloseShot,
+        AreaShot,
+    }
+
+    /// Everything needed to pick a run back up after the game closes
+    #[derive(Serialize, Deserialize)]
+    pub struct RunSnapshot {
+        pub character: usize,
+        pub stage: usize,
+        pub position: Vec2,
+        pub player: Player,
+        pub dash: Dash,
+        pub inventory: Inventory,
+        pub offers: LevelUpOffers,
+        pub weapons: Vec<WeaponSnapshot>,
+        pub wave_time: f32,
+        pub elite_time: f32,
+        #[serde(default)]
+        pub boss_time: f32,
+        //Run clock seconds each broken prop broke at
+        #[serde(default)]
+        pub broken_props: Vec<(PropId, f32)>,
+        #[serde(default)]
+        pub run_time: f32,
+        pub enemies: Vec<(Enemy, Vec2)>,
+        pub orbs: Vec<(i64, Vec2)>,
+        pub pickups: Vec<(Pickup, Vec2)>,
+        //Closed while a chest was open, it gets dropped again on resume
+        pub pending_chest: bool,
+        pub rng_seed: u64,
+    }
+
+    /// Snapshot loaded from the main menu, applied once gameplay starts
+    #[derive(Resource, Default)]
+    pub struct PendingResume(pub Option<RunSnapshot>);
+
+    #[derive(Component)]
+    pub struct ContinueButtonUI;
+
+    /// One finished run, every field defaults so older files still load
+    #[derive(Clone, Default, Serialize, Deserialize)]
+    #[serde(default)]
+    pub struct RunRecord {
+        //Seconds since the unix epoch
+        pub finished_at: u64,
+        pub character: String,
+        pub stage: String,
+        pub survived: f32,
+        pub level: i64,
+        pub kills: u32,
+        pub gold: u32,
+        //Display names so renamed or removed upgrades can't break old files
+        pub build: Vec<String>,
+    }
+
+    #[derive(Resource, Serialize, Deserialize)]
+    pub struct RunHistory {
+        #[serde(default)]
+        pub version: u32,
+        #[serde(default)]
+        pub runs: Vec<RunRecord>,
+    }
+
+    /// Time survived this run, waves only tick on stages that have them
+    #[derive(Resource, Default)]
+    pub struct RunClock(pub Stopwatch);
+
+    /// Column the high score table is ordered by
+    #[derive(Resource, Clone, Copy, PartialEq, Eq, Default, Debug, EnumIter)]
+    pub enum HistorySort {
+        #[default]
+        Survived,
+        Level,
+        Kills,
+        Gold,
+        Recent,
+    }
+
+    #[derive(Component)]
+    pub struct HistoryUI;
+
+    #[derive(Component)]
+    pub struct HistoryButtonUI;
+
+    #[derive(Component)]
+    pub struct HistorySortButtonUI(pub HistorySort);
+
+    #[derive(Component)]
+    pub struct HistoryBackButtonUI;
+
+    #[derive(Component)]
+    pub struct PauseUI;
+
+    #[derive(Component)]
+    pub struct ResumeButtonUI;
+
+    #[derive(Component)]
+    pub struct SettingsButtonUI;
+
+    #[derive(Component)]
+    pub struct GiveUpButtonUI;
+
     #[derive(Component)]
     pub struct HeaderBarUI;
 
@@ -174,6 +694,9 @@ pub mod prelude {
     #[derive(Component)]
     pub struct HealthUI;
 
+    #[derive(Component)]
+    pub struct DashUI;
+
     #[derive(Component)]
     pub struct MainMenuUI;
 
@@ -186,6 +709,45 @@ pub mod prelude {
     #[derive(Component)]
     pub struct AboutButtonUI;
 
+    #[derive(Component)]
+    pub struct StageButtonUI;
+
+    #[derive(Component)]
+    pub struct StageSelectUI;
+
+    #[derive(Component)]
+    pub struct StageCardUI(pub usize);
+
+    #[derive(Component)]
+    pub struct StageBackButton;
+
+    #[derive(Component)]
+    pub struct CharacterSelectUI;
+
+    #[derive(Component)]
+    pub struct CharacterButtonUI(pub usize);
+
+    #[derive(Component)]
+    pub struct CharacterBackButton;
+
+    #[derive(Component)]
+    pub struct AimModeButtonUI;
+
+    #[derive(Component)]
+    pub struct ControlsButtonUI;
+
+    #[derive(Component)]
+    pub struct ControlsUI;
+
+    #[derive(Component)]
+    pub struct ControlsBackButton;
+
+    #[derive(Component)]
+    pub struct ControlsResetButton;
+
+    #[derive(Component)]
+    pub struct BindingButtonUI(pub usize);
+
     #[derive(Component)]
     pub struct GamePlayEntity;
 
@@ -208,10 +770,15 @@ pub mod prelude {
     #[derive(Resource)]
     pub struct AboutShown(pub bool);
 
+    #[derive(Resource)]
+    pub struct ControlsShown(pub bool);
+
     #[derive(Resource)]
     pub struct WaveManager {
         pub global_time: Stopwatch,
         pub waves: Vec<Wave>,
+        pub elite_timer: Timer,
+        pub boss_timer: Timer,
     }
 
     pub struct Wave {
@@ -220,6 +787,73 @@ pub mod prelude {
         pub to_spawn: Enemy,
     }
 
+    #[derive(Clone, Serialize, Deserialize)]
+    pub struct StageWave {
+        pub spawn_interval: f32,
+        pub wave_size: i32,
+        pub enemy: Enemy,
+    }
+
+    #[derive(Clone, Copy, Serialize, Deserialize)]
+    pub enum StageBounds {
+        Open,
+        //Background streams in around the camera forever
+        Endless,
+        //Half size of the walled square
+        Walled(f32),
+    }
+
+    #[derive(Clone, Serialize, Deserialize)]
+    pub struct Obstacle {
+        pub position: Vec2,
+        pub size: Vec2,
+        pub color: Color,
+    }
+
+    #[derive(Clone, Serialize, Deserialize)]
+    pub struct Stage {
+        pub name: String,
+        pub description: String,
+        pub tile: String,
+        pub tile_tint: Color,
+        //Tiles spawned in each direction from the center
+        pub tiles: i32,
+        pub bounds: StageBounds,
+        pub obstacles: Vec<Obstacle>,
+        pub waves: Vec<StageWave>,
+        pub music: String,
+        #[serde(default)]
+        pub decorations: Vec<String>,
+        #[serde(default)]
+        pub decorations_per_chunk: u32,
+        #[serde(default)]
+        pub seed: u64,
+        //Endless stages place these in every chunk, relative to the chunk center
+        #[serde(default)]
+        pub props: Vec<StageProp>,
+        #[serde(default)]
+        pub loot: Vec<LootEntry>,
+        #[serde(default)]
+        pub prop_respawn: Option<f32>,
+    }
+
+    /// Streamed background chunks by chunk coordinate
+    #[derive(Resource, Default)]
+    pub struct LoadedChunks(pub HashMap<IVec2, Entity>);
+
+    #[derive(Resource)]
+    pub struct Stages(pub Vec<Stage>);
+
+    /// Index into `Stages` used by the next run
+    #[derive(Resource, Default)]
+    pub struct SelectedStage(pub usize);
+
+    #[derive(Resource, Default)]
+    pub struct Music {
+        pub track: String,
+        pub sink: Handle<AudioSink>,
+    }
+
     #[derive(Component)]
     pub struct LevelUpParticle;
 
@@ -0,0 +1,194 @@
+use bevy::ui::UiSystem;
+
+use crate::prelude::*;
+
+//Seconds between steps while a direction is held
+const REPEAT_DELAY: f32 = 0.25;
+
+pub struct MenuPlugin;
+
+impl Plugin for MenuPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<MenuFocus>().add_system(
+            navigate_menu
+                .in_base_set(CoreSet::PreUpdate)
+                .after(UiSystem::Focus),
+        );
+    }
+}
+
+impl Default for MenuFocus {
+    fn default() -> Self {
+        Self {
+            focused: None,
+            pressed: None,
+            keyboard: false,
+            repeat: Timer::from_seconds(REPEAT_DELAY, TimerMode::Once),
+        }
+    }
+}
+
+//Overlays use a global z index, only their buttons should be reachable while they are up
+fn layer(entity: Entity, nodes: &Query<(Option<&Parent>, Option<&ZIndex>)>) -> i32 {
+    let mut current = Some(entity);
+    while let Some(entity) = current {
+        let Ok((parent, z_index)) = nodes.get(entity) else {
+            break;
+        };
+        if let Some(ZIndex::Global(z)) = z_index {
+            return *z;
+        }
+        current = parent.map(|parent| parent.get());
+    }
+    0
+}
+
+//Drives button Interaction from the keyboard or gamepad so every button system works unchanged
+fn navigate_menu(
+    mut focus: ResMut<MenuFocus>,
+    mut buttons: Query<
+        (
+            Entity,
+            &GlobalTransform,
+            &ComputedVisibility,
+            &mut Interaction,
+        ),
+        With<Button>,
+    >,
+    nodes: Query<(Option<&Parent>, Option<&ZIndex>)>,
+    actions: Res<ActionState>,
+    rebinding: Res<RebindingAction>,
+    mut cursor: EventReader<CursorMoved>,
+    time: Res<Time>,
+) {
+    let mouse_moved = cursor.iter().count() > 0;
+
+    //Release last frame's keyboard click, the mouse does this itself on release
+    if let Some(pressed) = focus.pressed.take() {
+        if let Ok((_, _, _, mut interaction)) = buttons.get_mut(pressed) {
+            if *interaction == Interaction::Clicked {
+                *interaction = Interaction::Hovered;
+            }
+        }
+    }
+
+    //The next key press belongs to the binding being changed
+    if rebinding.0.is_some() {
+        return;
+    }
+
+    let mut candidates = buttons
+        .iter()
+        .filter(|(_, _, visibility, _)| visibility.is_visible_in_hierarchy())
+        .map(|(entity, transform, _, _)| {
+            (
+                entity,
+                transform.translation().truncate(),
+                layer(entity, &nodes),
+            )
+        })
+        .collect::<Vec<_>>();
+    let top = candidates.iter().map(|(_, _, layer)| *layer).max();
+    candidates.retain(|(_, _, layer)| Some(*layer) == top);
+    //Reading order, top to bottom then left to right
+    candidates.sort_by(|(_, a, _), (_, b, _)| {
+        a.y.partial_cmp(&b.y)
+            .unwrap()
+            .then(a.x.partial_cmp(&b.x).unwrap())
+    });
+
+    //Moving the mouse onto a button takes over the focus, a resting cursor shouldn't fight the keys
+    let hovered = buttons
+        .iter()
+        .filter(|_| mouse_moved)
+        .find(|(entity, _, _, interaction)| {
+            **interaction != Interaction::None && Some(*entity) != focus.focused
+        })
+        .map(|(entity, _, _, _)| entity);
+    if let Some(hovered) = hovered {
+        focus.focused = Some(hovered);
+        focus.keyboard = false;
+    }
+
+    let valid = focus
+        .focused
+        .is_some_and(|focused| candidates.iter().any(|(entity, _, _)| *entity == focused));
+    if !valid {
+        //Screens get rebuilt under a keyboard user, keep them on something
+        focus.focused = if focus.keyboard {
+            candidates.first().map(|(entity, _, _)| *entity)
+        } else {
+            None
+        };
+    }
+
+    let direction = actions.movement();
+    let confirm = actions.just_pressed(InputAction::Confirm);
+
+    let mut step = false;
+    if direction.length() > 0.5 {
+        focus.repeat.tick(time.delta());
+        if !focus.keyboard || focus.repeat.finished() {
+            step = true;
+            focus.repeat.reset();
+        }
+    } else {
+        //Let go, so the next press moves right away (ticking updates finished)
+        let duration = focus.repeat.duration();
+        focus.repeat.set_elapsed(duration);
+        focus.repeat.tick(time.delta());
+    }
+
+    if (step || confirm) && !focus.keyboard {
+        focus.keyboard = true;
+        if focus.focused.is_none() {
+            //First press only picks something to highlight
+            focus.focused = candidates.first().map(|(entity, _, _)| *entity);
+            return;
+        }
+    }
+
+    let Some(focused) = focus.focused else {
+        return;
+    };
+
+    if step {
+        let from = candidates
+            .iter()
+            .find(|(entity, _, _)| *entity == focused)
+            .map(|(_, position, _)| *position)
+            .unwrap_or_default();
+        //UI space grows downwards
+        let direction = Vec2::new(direction.x, -direction.y).normalize_or_zero();
+        let next = candidates
+            .iter()
+            .filter(|(entity, _, _)| *entity != focused)
+            .filter_map(|(entity, position, _)| {
+                let offset = *position - from;
+                let along = offset.dot(direction);
+                //Favor buttons lined up with the direction over closer ones off to the side
+                let across = offset.perp_dot(direction).abs();
+                (along > 1.0).then_some((*entity, along + across * 2.0))
+            })
+            .min_by(|(_, a), (_, b)| a.partial_cmp(b).unwrap())
+            .map(|(entity, _)| entity);
+        if let Some(next) = next {
+            if let Ok((_, _, _, mut interaction)) = buttons.get_mut(focused) {
+                *interaction = Interaction::None;
+            }
+            focus.focused = Some(next);
+        }
+    }
+
+    let Some(focused) = focus.focused else {
+        return;
+    };
+    if let Ok((_, _, _, mut interaction)) = buttons.get_mut(focused) {
+        if confirm {
+            *interaction = Interaction::Clicked;
+            focus.pressed = Some(focused);
+        } else if focus.keyboard && *interaction == Interaction::None {
+            *interaction = Interaction::Hovered;
+        }
+    }
+}
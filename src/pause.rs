@@ -0,0 +1,308 @@
+use bevy::window::WindowFocused;
+
+use crate::prelude::*;
+
+pub struct PausePlugin;
+
+impl Plugin for PausePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system(pause_game.in_set(OnUpdate(GameState::Gameplay)))
+            .add_systems(
+                (
+                    resume_game,
+                    resume_button_system,
+                    settings_button_system,
+                    give_up_button_system,
+                )
+                    .in_set(OnUpdate(GameState::Paused)),
+            )
+            .add_systems((spawn_pause_ui, freeze_world).in_schedule(OnEnter(GameState::Paused)))
+            .add_systems((despawn_pause_ui, unfreeze_world).in_schedule(OnExit(GameState::Paused)));
+    }
+}
+
+fn pause_game(
+    actions: Res<ActionState>,
+    mut focus_events: EventReader<WindowFocused>,
+    mut next_state: ResMut<NextState<GameState>>,
+) {
+    //Tabbing out shouldn't get you killed
+    let lost_focus = focus_events.iter().any(|event| !event.focused);
+    //A level up or chest already picked this frame would be lost, it pauses the game anyway
+    if next_state.0.is_some() {
+        return;
+    }
+    if actions.just_pressed(InputAction::Pause) || lost_focus {
+        next_state.set(GameState::Paused);
+    }
+}
+
+fn resume_game(
+    actions: Res<ActionState>,
+    mut controls: ResMut<ControlsShown>,
+    rebinding: Res<RebindingAction>,
+    mut next_state: ResMut<NextState<GameState>>,
+) {
+    //Escape cancelling a rebind shouldn't also close the menu
+    if !actions.just_pressed(InputAction::Pause) || rebinding.0.is_some() || rebinding.is_changed()
+    {
+        return;
+    }
+    //Back out of settings first
+    if controls.0 {
+        controls.0 = false;
+    } else {
+        next_state.set(GameState::Gameplay);
+    }
+}
+
+//Physics and music keep going outside of the gameplay systems
+fn freeze_world(
+    mut rapier: ResMut<RapierConfiguration>,
+    music: Res<Music>,
+    sinks: Res<Assets<AudioSink>>,
+) {
+    rapier.physics_pipeline_active = false;
+    if let Some(sink) = sinks.get(&music.sink) {
+        sink.pause();
+    }
+}
+
+fn unfreeze_world(
+    mut rapier: ResMut<RapierConfiguration>,
+    music: Res<Music>,
+    sinks: Res<Assets<AudioSink>>,
+) {
+    rapier.physics_pipeline_active = true;
+    if let Some(sink) = sinks.get(&music.sink) {
+        sink.play();
+    }
+}
+
+fn resume_button_system(
+    mut interaction_query: Query<
+        (&Interaction, &mut BackgroundColor),
+        (Changed<Interaction>, With<Button>, With<ResumeButtonUI>),
+    >,
+    controls: Res<ControlsShown>,
+    mut next_state: ResMut<NextState<GameState>>,
+) {
+    if controls.0 {
+        return;
+    }
+    for (interaction, mut color) in &mut interaction_query {
+        match *interaction {
+            Interaction::Clicked => {
+                *color = Color::RED.into();
+                next_state.set(GameState::Gameplay);
+            }
+            Interaction::Hovered => {
+                *color = Color::GREEN.into();
+            }
+            Interaction::None => {
+                *color = Color::DARK_GREEN.into();
+            }
+        }
+    }
+}
+
+fn settings_button_system(
+    mut interaction_query: Query<
+        (&Interaction, &mut BackgroundColor),
+        (Changed<Interaction>, With<Button>, With<SettingsButtonUI>),
+    >,
+    mut controls: ResMut<ControlsShown>,
+) {
+    if controls.0 {
+        return;
+    }
+    for (interaction, mut color) in &mut interaction_query {
+        match *interaction {
+            Interaction::Clicked => {
+                *color = Color::RED.into();
+                controls.0 = true;
+            }
+            Interaction::Hovered => {
+                *color = Color::GREEN.into();
+            }
+            Interaction::None => {
+                *color = Color::CRIMSON.into();
+            }
+        }
+    }
+}
+
+fn give_up_button_system(
+    mut interaction_query: Query<
+        (&Interaction, &mut BackgroundColor),
+        (Changed<Interaction>, With<Button>, With<GiveUpButtonUI>),
+    >,
+    controls: Res<ControlsShown>,
+    mut next_state: ResMut<NextState<GameState>>,
+) {
+    if controls.0 {
+        return;
+    }
+    for (interaction, mut color) in &mut interaction_query {
+        match *interaction {
+            Interaction::Clicked => {
+                *color = Color::RED.into();
+                next_state.set(GameState::GameOver);
+            }
+            Interaction::Hovered => {
+                *color = Color::GREEN.into();
+            }
+            Interaction::None => {
+                *color = Color::CRIMSON.into();
+            }
+        }
+    }
+}
+
+//Everything owned this run and the stats it adds up to
+fn build_overview(player: &Player, dash: &Dash, inventory: &Inventory) -> String {
+    let mut lines = inventory
+        .levels
+        .iter()
+        .map(|(upgrade, level)| {
+            if upgrade.is_evolution() {
+                upgrade.name().to_string()
+            } else {
+                format!("{} Lv {}/{}", upgrade.name(), level, upgrade.max_level())
+            }
+        })
+        .collect::<Vec<_>>();
+    lines.push(String::new());
+    lines.push(format!(
+        "Health {:.0}/{:.0}  Armor {}  Regen {:.1}/s",
+        player.health, player.max_health, player.armor, player.regen
+    ));
+    lines.push(format!(
+        "Speed {:.2}  Lifesteal {:.0}%  Luck {:.0}%",
+        player.speed,
+        player.lifesteal * 100.0,
+        player.luck * 100.0
+    ));
+    lines.push(format!(
+        "Dash {}x {:.2}s  Lives {}  Gold {}",
+        dash.max_charges,
+        dash.recharge.duration().as_secs_f32(),
+        player.revives,
+        player.gold
+    ));
+    lines.join("\n")
+}
+
+fn spawn_pause_ui(
+    mut commands: Commands,
+    assets: Res<AssetServer>,
+    player: Query<(&Player, &Dash)>,
+    inventory: Res<Inventory>,
+) {
+    let font = assets.load("fonts/pointfree.ttf");
+    let (player, dash) = player.single();
+
+    let pause_parent = (
+        NodeBundle {
+            style: Style {
+                size: Size::new(Val::Percent(100.0), Val::Percent(100.0)),
+                position_type: PositionType::Absolute,
+                align_items: AlignItems::Center,
+                justify_content: JustifyContent::Center,
+                ..default()
+            },
+            background_color: Color::rgba(0.0, 0.0, 0.0, 0.5).into(),
+            ..default()
+        },
+        PauseUI,
+    );
+
+    let pause_popup = NodeBundle {
+        style: Style {
+            size: Size::new(Val::Percent(70.0), Val::Percent(80.0)),
+            position_type: PositionType::Relative,
+            align_items: AlignItems::Center,
+            flex_direction: FlexDirection::Column,
+            justify_content: JustifyContent::SpaceAround,
+            ..default()
+        },
+        background_color: Color::DARK_GRAY.into(),
+        ..default()
+    };
+
+    let title_text = TextBundle::from_section(
+        "Paused",
+        TextStyle {
+            font: font.clone(),
+            font_size: 56.0,
+            color: Color::rgb(0.9, 0.9, 0.9),
+        },
+    );
+
+    let overview_text = TextBundle::from_section(
+        build_overview(player, dash, &inventory),
+        TextStyle {
+            font: font.clone(),
+            font_size: 22.0,
+            color: Color::rgb(0.7, 1.0, 0.7),
+        },
+    )
+    .with_text_alignment(TextAlignment::Center);
+
+    let button_row = NodeBundle {
+        style: Style {
+            size: Size::new(Val::Percent(90.0), Val::Percent(14.0)),
+            flex_direction: FlexDirection::Row,
+            justify_content: JustifyContent::SpaceAround,
+            ..default()
+        },
+        ..default()
+    };
+
+    commands.spawn(pause_parent).with_children(|commands| {
+        commands.spawn(pause_popup).with_children(|commands| {
+            commands.spawn(title_text);
+            commands.spawn(overview_text);
+            commands.spawn(button_row).with_children(|commands| {
+                for action in 0..3 {
+                    let (label, color) = match action {
+                        0 => ("Resume", Color::DARK_GREEN),
+                        1 => ("Settings", Color::CRIMSON),
+                        _ => ("Give Up", Color::CRIMSON),
+                    };
+                    let mut button = commands.spawn(ButtonBundle {
+                        style: Style {
+                            size: Size::new(Val::Percent(30.0), Val::Percent(100.0)),
+                            justify_content: JustifyContent::Center,
+                            align_items: AlignItems::Center,
+                            ..default()
+                        },
+                        background_color: color.into(),
+                        ..default()
+                    });
+                    match action {
+                        0 => button.insert(ResumeButtonUI),
+                        1 => button.insert(SettingsButtonUI),
+                        _ => button.insert(GiveUpButtonUI),
+                    };
+                    button.with_children(|commands| {
+                        commands.spawn(TextBundle::from_section(
+                            label,
+                            TextStyle {
+                                font: font.clone(),
+                                font_size: 32.0,
+                                color: Color::rgb(0.9, 0.9, 0.9),
+                            },
+                        ));
+                    });
+                }
+            });
+        });
+    });
+}
+
+fn despawn_pause_ui(mut commands: Commands, ui: Query<Entity, With<PauseUI>>) {
+    for ui in &ui {
+        commands.entity(ui).despawn_recursive();
+    }
+}
@@ -1,8 +1,19 @@
+use bevy::ecs::system::SystemParam;
+
 use crate::{
-    attack::{spawn_area_shot, spawn_close_shot, spawn_whip},
+    attack::{
+        area_shot_targeting, close_shot_targeting, damage_enemy, spawn_area_shot, spawn_close_shot,
+        spawn_whip,
+    },
     prelude::*,
+    ui::spawn_world_text,
 };
 
+const REVIVE_HEALTH: f32 = 0.5;
+const REVIVE_RADIUS: f32 = 5.0;
+const REVIVE_DAMAGE: f32 = 25.0;
+const REVIVE_INVULNERABILITY: f32 = 2.0;
+
 pub struct PlayerPlugin;
 
 impl Plugin for PlayerPlugin {
@@ -10,17 +21,239 @@ impl Plugin for PlayerPlugin {
         app.add_system(spawn_player.in_schedule(OnEnter(GameState::StartingLoop)))
             .add_systems(
                 (
+                    player_dash.before(player_movement),
                     player_movement,
+                    update_afterimages,
+                    player_revive.before(player_game_over),
+                    update_revive_rings,
+                    update_pickup_radius.before(player_exp_start_pickup),
                     player_exp_start_pickup,
+                    player_collect_pickups,
                     player_gain_exp,
-                    player_level_up,
+                    player_level_up.after(player_collect_pickups),
                     player_game_over,
+                    player_invulnerability,
+                    player_regen,
+                    player_lifesteal,
                 )
                     .in_set(OnUpdate(GameState::Gameplay)),
             );
     }
 }
 
+impl Invulnerability {
+    pub fn active(&self) -> bool {
+        !self.timer.finished()
+    }
+
+    /// Extends the current invulnerability to at least `seconds` from now
+    pub fn grant(&mut self, seconds: f32) {
+        let remaining = self.timer.duration().as_secs_f32() - self.timer.elapsed_secs();
+        if remaining < seconds {
+            self.timer = Timer::from_seconds(seconds, TimerMode::Once);
+        }
+    }
+}
+
+impl Dash {
+    pub fn is_dashing(&self) -> bool {
+        !self.active.finished()
+    }
+}
+
+fn player_dash(
+    mut commands: Commands,
+    mut player: Query<(&Transform, &Sprite, &Handle<Image>, &Player, &mut Dash)>,
+    actions: Res<ActionState>,
+    time: Res<Time>,
+) {
+    let (transform, sprite, texture, player, mut dash) = player.single_mut();
+
+    if dash.charges < dash.max_charges {
+        dash.recharge.tick(time.delta());
+        if dash.recharge.just_finished() {
+            dash.charges += 1;
+        }
+    }
+
+    dash.active.tick(time.delta());
+
+    if actions.just_pressed(InputAction::Dash) && dash.charges > 0 && !dash.is_dashing() {
+        //Dash where the stick points, or straight ahead when standing still
+        dash.direction = actions
+            .movement()
+            .try_normalize()
+            .unwrap_or(match player.facing {
+                Facing::Left => Vec2::NEG_X,
+                Facing::Right => Vec2::X,
+            });
+        dash.charges -= 1;
+        dash.active = Timer::from_seconds(dash.duration, TimerMode::Once);
+        dash.afterimage.reset();
+    }
+
+    if dash.is_dashing() {
+        dash.afterimage.tick(time.delta());
+        if dash.afterimage.just_finished() {
+            commands.spawn((
+                SpriteBundle {
+                    transform: Transform::from_translation(
+                        transform.translation - Vec3::new(0.0, 0.0, 1.0),
+                    ),
+                    texture: texture.clone(),
+                    sprite: Sprite {
+                        color: Color::rgba(0.6, 0.8, 1.0, 0.6),
+                        ..sprite.clone()
+                    },
+                    ..default()
+                },
+                Afterimage {
+                    lifetime: Timer::from_seconds(0.25, TimerMode::Once),
+                },
+                GamePlayEntity,
+                Name::new("Afterimage"),
+            ));
+        }
+    }
+}
+
+fn update_afterimages(
+    mut commands: Commands,
+    mut afterimages: Query<(Entity, &mut Sprite, &mut Afterimage)>,
+    time: Res<Time>,
+) {
+    for (entity, mut sprite, mut afterimage) in &mut afterimages {
+        afterimage.lifetime.tick(time.delta());
+        sprite.color.set_a(0.6 * afterimage.lifetime.percent_left());
+        if afterimage.lifetime.just_finished() {
+            commands.entity(entity).despawn_recursive();
+        }
+    }
+}
+
+fn player_invulnerability(
+    mut player: Query<(&mut Sprite, &mut Invulnerability), With<Player>>,
+    time: Res<Time>,
+) {
+    let (mut sprite, mut invulnerability) = player.single_mut();
+
+    invulnerability.timer.tick(time.delta());
+    invulnerability.flash.tick(time.delta());
+
+    if invulnerability.active() {
+        if invulnerability.flash.just_finished() {
+            let alpha = if sprite.color.a() < 1.0 { 1.0 } else { 0.3 };
+            sprite.color.set_a(alpha);
+        }
+    } else {
+        sprite.color.set_a(1.0);
+    }
+}
+
+fn player_regen(mut player: Query<&mut Player>, time: Res<Time>) {
+    let mut player = player.single_mut();
+
+    if player.health > 0.0 {
+        player.health =
+            (player.health + player.regen * time.delta_seconds()).min(player.max_health);
+    }
+}
+
+fn player_lifesteal(mut player: Query<&mut Player>, mut damage_events: EventReader<DamageDealt>) {
+    let mut player = player.single_mut();
+
+    let dealt: f32 = damage_events.iter().map(|damage| damage.0).sum();
+    if player.health > 0.0 {
+        player.health = (player.health + dealt * player.lifesteal).min(player.max_health);
+    }
+}
+
+fn player_revive(
+    mut commands: Commands,
+    assets: Res<AssetServer>,
+    audio: Res<Audio>,
+    mut player: Query<(&Transform, &mut Player, &mut Invulnerability)>,
+    mut enemies: Query<(&mut Transform, &mut Enemy), Without<Player>>,
+    mut damage_events: EventWriter<DamageDealt>,
+) {
+    let (player_transform, mut player, mut invulnerability) = player.single_mut();
+
+    if player.health > 0.0 || player.revives == 0 {
+        return;
+    }
+
+    player.revives -= 1;
+    player.health = player.max_health * REVIVE_HEALTH;
+    invulnerability.grant(REVIVE_INVULNERABILITY);
+
+    let center = player_transform.translation.truncate();
+    for (mut transform, mut enemy) in &mut enemies {
+        let offset = transform.translation.truncate() - center;
+        if offset.length() < REVIVE_RADIUS {
+            //Shove everything out to the edge of the blast
+            let push = offset.normalize_or_zero() * (REVIVE_RADIUS - offset.length());
+            transform.translation += push.extend(0.0);
+            damage_enemy(
+                &mut commands,
+                &assets,
+                &mut damage_events,
+                &mut enemy,
+                &transform,
+                REVIVE_DAMAGE,
+            );
+        }
+    }
+
+    commands.spawn((
+        SpriteBundle {
+            transform: Transform::from_translation(center.extend(150.0)),
+            texture: assets.load("revive_ring.png"),
+            sprite: Sprite {
+                custom_size: Some(Vec2::splat(1.0)),
+                ..default()
+            },
+            ..default()
+        },
+        ReviveRing {
+            lifetime: Timer::from_seconds(0.6, TimerMode::Once),
+        },
+        GamePlayEntity,
+        Name::new("Revive Ring"),
+    ));
+    spawn_world_text(
+        &mut commands,
+        &assets,
+        center,
+        "REVIVED!",
+        Color::rgb(1.0, 0.9, 0.4),
+    );
+    audio.play_with_settings(
+        assets.load("revive.wav"),
+        PlaybackSettings {
+            repeat: false,
+            volume: 0.8,
+            speed: 1.0,
+        },
+    );
+}
+
+fn update_revive_rings(
+    mut commands: Commands,
+    mut rings: Query<(Entity, &mut Transform, &mut Sprite, &mut ReviveRing)>,
+    time: Res<Time>,
+) {
+    for (entity, mut transform, mut sprite, mut ring) in &mut rings {
+        ring.lifetime.tick(time.delta());
+        //Grow out to the push radius while fading
+        let percent = ring.lifetime.percent();
+        transform.scale = Vec3::splat(REVIVE_RADIUS * 2.0 * percent);
+        sprite.color.set_a(1.0 - percent);
+        if ring.lifetime.just_finished() {
+            commands.entity(entity).despawn_recursive();
+        }
+    }
+}
+
 fn player_game_over(
     player: Query<&Player>,
     mut game_state: ResMut<NextState<GameState>>,
@@ -29,7 +262,8 @@ fn player_game_over(
 ) {
     let player = player.single();
 
-    if player.health <= 0.0 {
+    //A hit landing after player_revive this frame leaves the revive for next frame
+    if player.health <= 0.0 && player.revives == 0 {
         audio.play_with_settings(
             assets.load("death.wav"),
             PlaybackSettings {
@@ -47,13 +281,30 @@ fn player_level_up(
     mut game_state: ResMut<NextState<GameState>>,
     audio: Res<Audio>,
     assets: Res<AssetServer>,
+    curve: Res<ExpCurve>,
 ) {
     let mut player = player.single_mut();
 
-    if player.exp >= player.next_level_exp {
-        player.exp = 0;
-        player.next_level_exp = (player.next_level_exp as f32 * 1.4) as i64;
+    //A big pickup can be worth several levels, keep the overflow and queue them up
+    while player.exp >= player.next_level_exp {
+        player.exp -= player.next_level_exp;
         player.level += 1;
+        player.next_level_exp = curve.exp_to_next(player.level);
+        player.pending_levels += 1;
+        let passive = player.passive;
+        passive.apply(&mut player);
+    }
+
+    //Back in gameplay after picking an upgrade, show the next queued screen
+    //Chests go first and the levels wait for the next return
+    if game_state.0.is_some() {
+        return;
+    }
+    if player.pending_chests > 0 {
+        player.pending_chests -= 1;
+        game_state.set(GameState::Chest);
+    } else if player.pending_levels > 0 {
+        player.pending_levels -= 1;
         audio.play_with_settings(
             assets.load("level_up.wav"),
             PlaybackSettings {
@@ -66,15 +317,29 @@ fn player_level_up(
     }
 }
 
+fn update_pickup_radius(
+    player: Query<&Player>,
+    mut pickup: Query<&mut Collider, With<PickupRadius>>,
+    mut last_radius: Local<f32>,
+) {
+    let player = player.single();
+
+    //Only rebuild the shape when the stat actually moves
+    if player.magnet != *last_radius {
+        *last_radius = player.magnet;
+        *pickup.single_mut() = Collider::ball(player.magnet);
+    }
+}
+
 fn player_exp_start_pickup(
-    player: Query<(&Transform, &Collider), With<Player>>,
+    pickup: Query<(&GlobalTransform, &Collider), With<PickupRadius>>,
     rapier_context: Res<RapierContext>,
     mut orbs: Query<&mut ExpOrb>,
 ) {
-    let (transform, collider) = player.single();
+    let (transform, collider) = pickup.single();
 
     rapier_context.intersections_with_shape(
-        transform.translation.truncate(),
+        transform.translation().truncate(),
         0.0,
         collider,
         QueryFilter::new(),
@@ -87,44 +352,210 @@ fn player_exp_start_pickup(
     );
 }
 
+const BOMB_RADIUS: f32 = 12.0;
+
+/// What a pickup can touch besides the player, plus the sounds it makes
+#[derive(SystemParam)]
+struct PickupEffects<'w, 's> {
+    assets: Res<'w, AssetServer>,
+    audio: Res<'w, Audio>,
+    coin: Res<'w, CoinAssets>,
+    orbs: Query<'w, 's, &'static mut ExpOrb>,
+    enemies: Query<'w, 's, (&'static Transform, &'static mut Enemy), Without<Player>>,
+    damage_events: EventWriter<'w, DamageDealt>,
+}
+
+fn player_collect_pickups(
+    mut commands: Commands,
+    mut player: Query<(&Transform, &Collider, &mut Player)>,
+    pickups: Query<&Pickup>,
+    rapier_context: Res<RapierContext>,
+    mut effects: PickupEffects,
+) {
+    let (transform, collider, mut player) = player.single_mut();
+    let center = transform.translation.truncate();
+
+    let mut collected = Vec::new();
+    rapier_context.intersections_with_shape(center, 0.0, collider, QueryFilter::new(), |entity| {
+        if let Ok(pickup) = pickups.get(entity) {
+            commands.entity(entity).despawn_recursive();
+            collected.push(*pickup);
+        }
+        true
+    });
+
+    for pickup in collected {
+        match pickup {
+            Pickup::Health(amount) => {
+                player.health = (player.health + amount).min(player.max_health);
+            }
+            Pickup::Magnet => {
+                for mut orb in &mut effects.orbs {
+                    orb.collecting = true;
+                }
+            }
+            Pickup::Gold(amount) => {
+                player.gold += amount;
+                spawn_world_text(
+                    &mut commands,
+                    &effects.assets,
+                    center,
+                    &format!("+{}g", amount),
+                    Color::rgb(1.0, 0.85, 0.2),
+                );
+            }
+            Pickup::Bomb(damage) => {
+                for (enemy_transform, mut enemy) in &mut effects.enemies {
+                    if enemy_transform.translation.truncate().distance(center) < BOMB_RADIUS {
+                        damage_enemy(
+                            &mut commands,
+                            &effects.assets,
+                            &mut effects.damage_events,
+                            &mut enemy,
+                            enemy_transform,
+                            damage,
+                        );
+                    }
+                }
+                effects.audio.play_with_settings(
+                    effects.assets.load("bomb.wav"),
+                    PlaybackSettings {
+                        repeat: false,
+                        volume: 0.8,
+                        speed: 1.0,
+                    },
+                );
+                continue;
+            }
+            Pickup::Chest => {
+                player.pending_chests += 1;
+                continue;
+            }
+        }
+        effects.audio.play_with_settings(
+            effects.coin.audio.clone(),
+            PlaybackSettings {
+                repeat: false,
+                volume: 0.8,
+                speed: 0.7,
+            },
+        );
+    }
+}
+
 fn player_gain_exp(
     mut commands: Commands,
-    orbs: Query<(Entity, &Transform, &ExpOrb)>,
+    rapier_context: Res<RapierContext>,
+    orbs: Query<&ExpOrb>,
     coin: Res<CoinAssets>,
     audio: Res<Audio>,
-    mut player: Query<(&Transform, &mut Player), Without<ExpOrb>>,
+    mut player: Query<(Entity, &mut Player)>,
 ) {
-    let (player_transform, mut player) = player.single_mut();
-
-    for (entity, transform, orb) in &orbs {
-        //TODO probably should use physics for this
-        if Vec2::distance(
-            transform.translation.truncate(),
-            player_transform.translation.truncate(),
-        ) < 0.3
-        {
-            //TODO event for sound
-            player.exp += orb.value;
-            audio.play_with_settings(
-                coin.audio.clone(),
-                PlaybackSettings {
-                    repeat: false,
-                    volume: 0.5,
-                    speed: 1.0,
-                },
-            );
-            commands.entity(entity).despawn_recursive();
+    let (player_entity, mut player) = player.single_mut();
+
+    //Sensor pairs stay active while touching, so orbs that landed during a level up still count
+    //Only the body counts, the pickup radius sensor is its own entity
+    let collected = rapier_context
+        .intersections_with(player_entity)
+        .filter(|(_, _, intersecting)| *intersecting)
+        .filter_map(|(first, second, _)| {
+            let orb = if first == player_entity {
+                second
+            } else {
+                first
+            };
+            orbs.get(orb).ok().map(|exp_orb| (orb, exp_orb.value))
+        })
+        .collect::<Vec<_>>();
+
+    for (orb, value) in collected {
+        //TODO event for sound
+        player.exp += value;
+        audio.play_with_settings(
+            coin.audio.clone(),
+            PlaybackSettings {
+                repeat: false,
+                volume: 0.5,
+                speed: 1.0,
+            },
+        );
+        commands.entity(orb).despawn_recursive();
+    }
+}
+
+//A dash cuts straight through enemies, only walls stop it
+fn walk_filter(dashing: bool) -> CollisionGroups {
+    if dashing {
+        CollisionGroups::new(PLAYER_GROUP, WALL_GROUP)
+    } else {
+        CollisionGroups::new(PLAYER_GROUP, WALL_GROUP | ENEMY_GROUP)
+    }
+}
+
+fn spawn_starting_weapon(
+    commands: &mut Commands,
+    assets: &AssetServer,
+    weapon: &WeaponUpgrade,
+) -> Option<Entity> {
+    match weapon {
+        WeaponUpgrade::Whip => Some(spawn_whip(commands, assets)),
+        WeaponUpgrade::CloseShot => Some(spawn_close_shot(commands, close_shot_targeting(false))),
+        WeaponUpgrade::AreaShot => Some(spawn_area_shot(commands, area_shot_targeting(false))),
+        _ => {
+            warn!("{} can't be a starting weapon", weapon.name());
+            None
         }
     }
 }
 
-fn spawn_player(mut commands: Commands, assets: Res<AssetServer>) {
-    let whip = spawn_whip(&mut commands, &assets);
-    commands
+fn spawn_player(
+    mut commands: Commands,
+    assets: Res<AssetServer>,
+    characters: Res<Characters>,
+    selected: Res<SelectedCharacter>,
+    curve: Res<ExpCurve>,
+    mut inventory: ResMut<Inventory>,
+    profile: Res<Profile>,
+) {
+    let character = characters.0.get(selected.0).unwrap_or(&characters.0[0]);
+    //Fresh loadout holding just the starting weapon
+    *inventory = Inventory::default();
+    inventory.add(&character.starting_weapon);
+    let weapon = spawn_starting_weapon(&mut commands, &assets, &character.starting_weapon);
+
+    let mut stats = Player {
+        exp: 0,
+        next_level_exp: curve.exp_to_next(1),
+        level: 1,
+        pending_levels: 0,
+        pending_chests: 0,
+        speed: character.speed,
+        velocity: Vec2::ZERO,
+        acceleration: 12.0,
+        deceleration: 16.0,
+        health: character.health,
+        max_health: character.health,
+        armor: character.armor,
+        regen: character.regen,
+        lifesteal: 0.0,
+        magnet: 2.0,
+        revives: 0,
+        gold: 0,
+        kills: 0,
+        luck: 0.1,
+        might: 1.0,
+        hit_invulnerability: 0.6,
+        passive: character.passive,
+        facing: Facing::Left,
+    };
+    //Shop bonuses carry over from previous runs
+    profile.apply(&mut stats);
+
+    let player = commands
         .spawn((
             SpriteBundle {
                 transform: Transform::from_xyz(0.0, 0.0, 100.0),
-                texture: assets.load("player_1.png"),
+                texture: assets.load(&character.sprite_1),
                 sprite: Sprite {
                     custom_size: Some(Vec2::new(63.0 * PIXEL_TO_WORLD, 113.0 * PIXEL_TO_WORLD)),
                     ..default()
@@ -132,49 +563,109 @@ fn spawn_player(mut commands: Commands, assets: Res<AssetServer>) {
                 ..default()
             },
             TwoFrameAnimation {
-                frame_1: assets.load("player_1.png"),
-                frame_2: assets.load("player_2.png"),
+                frame_1: assets.load(&character.sprite_1),
+                frame_2: assets.load(&character.sprite_2),
                 current_frame: false,
                 timer: Timer::from_seconds(0.3, TimerMode::Repeating),
             },
-            Player {
-                exp: 0,
-                next_level_exp: 5,
-                level: 1,
-                speed: 3.0,
-                health: 100.0,
-                max_health: 100.0,
-                facing: Facing::Left,
+            stats,
+            Invulnerability {
+                timer: Timer::from_seconds(0.0, TimerMode::Once),
+                flash: Timer::from_seconds(0.08, TimerMode::Repeating),
+            },
+            Dash {
+                charges: 1,
+                max_charges: 1,
+                recharge: Timer::from_seconds(2.5, TimerMode::Repeating),
+                active: Timer::from_seconds(0.0, TimerMode::Once),
+                afterimage: Timer::from_seconds(0.04, TimerMode::Repeating),
+                direction: Vec2::X,
+                speed: 14.0,
+                duration: 0.2,
             },
             Name::new("Player"),
+            RigidBody::KinematicPositionBased,
             Collider::ball(0.9),
+            CollisionGroups::new(PLAYER_GROUP, Group::ALL),
+            KinematicCharacterController {
+                //Walk through pickups, scenery and the horde stop the player
+                filter_flags: QueryFilterFlags::EXCLUDE_SENSORS,
+                filter_groups: Some(walk_filter(false)),
+                ..default()
+            },
             GamePlayEntity,
         ))
-        .add_child(whip);
+        .id();
+
+    if let Some(weapon) = weapon {
+        commands.entity(player).add_child(weapon);
+    }
+
+    let pickup_radius = commands
+        .spawn((
+            TransformBundle::default(),
+            Collider::ball(2.0),
+            Sensor,
+            PickupRadius,
+            Name::new("Pickup Radius"),
+        ))
+        .id();
+    commands.entity(player).add_child(pickup_radius);
 }
 
 pub fn player_movement(
-    mut player: Query<(&mut Transform, &mut Sprite, &mut Player)>,
-    input: Res<Input<KeyCode>>,
+    mut player: Query<(
+        &Transform,
+        &mut Sprite,
+        &mut Player,
+        &Dash,
+        &mut KinematicCharacterController,
+    )>,
+    actions: Res<ActionState>,
+    cursor: Res<CursorPosition>,
+    aim_mode: Res<AimMode>,
     time: Res<Time>,
 ) {
-    let (mut transform, mut sprite, mut player) = player.single_mut();
-    if input.pressed(KeyCode::W) {
-        transform.translation.y += time.delta_seconds() * player.speed;
-    }
-    if input.pressed(KeyCode::S) {
-        transform.translation.y -= time.delta_seconds() * player.speed;
+    let (transform, mut sprite, mut player, dash, mut controller) = player.single_mut();
+
+    //Keep partial stick tilts but never let diagonals go faster than straight lines
+    let input = actions.movement().clamp_length_max(1.0);
+    let target_velocity = input * player.speed;
+    let rate = if input == Vec2::ZERO {
+        player.deceleration
+    } else {
+        player.acceleration
+    };
+    //Exponential ease towards the target so it feels the same at any framerate
+    let blend = 1.0 - (-rate * time.delta_seconds()).exp();
+    player.velocity = player.velocity.lerp(target_velocity, blend);
+
+    controller.filter_groups = Some(walk_filter(dash.is_dashing()));
+    if dash.is_dashing() {
+        //Come out of the dash still running the same way
+        player.velocity = dash.direction * player.speed;
+        controller.translation = Some(dash.direction * dash.speed * time.delta_seconds());
+    } else {
+        controller.translation = Some(player.velocity * time.delta_seconds());
     }
-    if input.pressed(KeyCode::A) {
-        transform.translation.x -= time.delta_seconds() * player.speed;
+
+    if input.x < 0.0 {
         sprite.flip_x = false;
         player.facing = Facing::Left;
     }
-    if input.pressed(KeyCode::D) {
-        transform.translation.x += time.delta_seconds() * player.speed;
+    if input.x > 0.0 {
         sprite.flip_x = true;
         player.facing = Facing::Right;
     }
-    transform.translation.x = transform.translation.x.clamp(-175.0, 175.0);
-    transform.translation.y = transform.translation.y.clamp(-175.0, 175.0);
+
+    //Face the crosshair instead of the walking direction when aiming manually
+    if *aim_mode == AimMode::Manual {
+        if cursor.world_position.x < transform.translation.x {
+            sprite.flip_x = false;
+            player.facing = Facing::Left;
+        } else {
+            sprite.flip_x = true;
+            player.facing = Facing::Right;
+        }
+    }
 }
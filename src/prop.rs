@@ -0,0 +1,236 @@
+use std::time::Duration;
+
+use bevy::ecs::system::SystemParam;
+
+use crate::{prelude::*, stage::selected_stage};
+
+pub struct PropPlugin;
+
+impl Plugin for PropPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<BrokenProps>()
+            .add_systems(
+                (spawn_stage_props, reset_broken_props)
+                    .in_schedule(OnEnter(GameState::StartingLoop)),
+            )
+            .add_systems(
+                (restore_broken_props, prop_break, prop_respawn)
+                    .chain()
+                    .in_set(OnUpdate(GameState::Gameplay)),
+            );
+    }
+}
+
+impl Pickup {
+    pub fn asset(&self) -> &str {
+        match self {
+            Pickup::Health(_) => "pizza_slice.png",
+            Pickup::Magnet => "magnet.png",
+            Pickup::Gold(_) => "gold.png",
+            Pickup::Bomb(_) => "bomb.png",
+            Pickup::Chest => "chest.png",
+        }
+    }
+}
+
+pub fn spawn_pickup(
+    commands: &mut Commands,
+    assets: &AssetServer,
+    pickup: Pickup,
+    position: Vec2,
+) -> Entity {
+    commands
+        .spawn((
+            SpriteBundle {
+                transform: Transform::from_translation(position.extend(100.0)),
+                texture: assets.load(pickup.asset()),
+                sprite: Sprite {
+                    custom_size: Some(Vec2::new(0.6, 0.6)),
+                    ..default()
+                },
+                ..default()
+            },
+            Collider::ball(0.3),
+            Sensor,
+            pickup,
+            GamePlayEntity,
+            Name::new("Pickup"),
+        ))
+        .id()
+}
+
+pub fn spawn_prop(
+    commands: &mut Commands,
+    assets: &AssetServer,
+    prop: &StageProp,
+    id: PropId,
+    respawn: Option<f32>,
+) -> Entity {
+    commands
+        .spawn((
+            SpriteBundle {
+                transform: Transform::from_translation(prop.position.extend(50.0)),
+                texture: assets.load(&prop.sprite),
+                sprite: Sprite {
+                    custom_size: Some(prop.size),
+                    ..default()
+                },
+                ..default()
+            },
+            //Sensor so weapons can find it without it blocking anyone
+            Collider::cuboid(prop.size.x / 2.0, prop.size.y / 2.0),
+            Sensor,
+            Prop {
+                id,
+                health: prop.health,
+                max_health: prop.health,
+                respawn: respawn.map(|seconds| Timer::from_seconds(seconds, TimerMode::Once)),
+                broken: false,
+            },
+            GamePlayEntity,
+            Name::new("Prop"),
+        ))
+        .id()
+}
+
+fn spawn_stage_props(
+    mut commands: Commands,
+    assets: Res<AssetServer>,
+    stages: Res<Stages>,
+    selected: Res<SelectedStage>,
+) {
+    let stage = selected_stage(&stages, &selected);
+    //Endless stages spawn theirs with each chunk
+    if matches!(stage.bounds, StageBounds::Endless) {
+        return;
+    }
+
+    for (index, prop) in stage.props.iter().enumerate() {
+        let id = PropId { chunk: None, index };
+        spawn_prop(&mut commands, &assets, prop, id, stage.prop_respawn);
+    }
+}
+
+fn reset_broken_props(mut broken: ResMut<BrokenProps>) {
+    broken.0.clear();
+}
+
+//Props come back broken if they were broken before their chunk unloaded
+fn restore_broken_props(
+    mut commands: Commands,
+    mut props: Query<(Entity, &mut Visibility, &mut Prop), Added<Prop>>,
+    mut broken: ResMut<BrokenProps>,
+    run_clock: Res<RunClock>,
+) {
+    let now = run_clock.0.elapsed_secs();
+    for (entity, mut visibility, mut prop) in &mut props {
+        let Some(broken_at) = broken.0.get(&prop.id).copied() else {
+            continue;
+        };
+        let Some(respawn) = &mut prop.respawn else {
+            commands.entity(entity).despawn_recursive();
+            continue;
+        };
+
+        let since = now - broken_at;
+        if since >= respawn.duration().as_secs_f32() {
+            //Would have come back while it was away
+            broken.0.remove(&prop.id);
+            continue;
+        }
+        respawn.set_elapsed(Duration::from_secs_f32(since));
+        prop.broken = true;
+        prop.health = 0.0;
+        *visibility = Visibility::Hidden;
+    }
+}
+
+/// Loot table of the stage being played
+#[derive(SystemParam)]
+struct StageLoot<'w> {
+    stages: Res<'w, Stages>,
+    selected: Res<'w, SelectedStage>,
+    rng: ResMut<'w, GlobalRng>,
+}
+
+impl StageLoot<'_> {
+    fn roll(&mut self) -> Option<Pickup> {
+        let loot = &selected_stage(&self.stages, &self.selected).loot;
+        let total: f32 = loot.iter().map(|entry| entry.weight).sum();
+        let mut roll = self.rng.f32() * total;
+
+        for entry in loot {
+            if roll < entry.weight {
+                return Some(entry.pickup);
+            }
+            roll -= entry.weight;
+        }
+        None
+    }
+}
+
+fn prop_break(
+    mut commands: Commands,
+    assets: Res<AssetServer>,
+    audio: Res<Audio>,
+    mut props: Query<(Entity, &GlobalTransform, &mut Visibility, &mut Prop)>,
+    mut loot: StageLoot,
+    mut broken: ResMut<BrokenProps>,
+    run_clock: Res<RunClock>,
+) {
+    for (entity, transform, mut visibility, mut prop) in &mut props {
+        if prop.broken || prop.health > 0.0 {
+            continue;
+        }
+        broken.0.insert(prop.id, run_clock.0.elapsed_secs());
+
+        if let Some(pickup) = loot.roll() {
+            spawn_pickup(
+                &mut commands,
+                &assets,
+                pickup,
+                transform.translation().truncate(),
+            );
+        }
+        audio.play_with_settings(
+            assets.load("prop_break.wav"),
+            PlaybackSettings {
+                repeat: false,
+                volume: 0.5,
+                speed: 1.0,
+            },
+        );
+
+        match &mut prop.respawn {
+            Some(respawn) => {
+                respawn.reset();
+                prop.broken = true;
+                *visibility = Visibility::Hidden;
+            }
+            None => commands.entity(entity).despawn_recursive(),
+        }
+    }
+}
+
+fn prop_respawn(
+    mut props: Query<(&mut Visibility, &mut Prop)>,
+    mut broken: ResMut<BrokenProps>,
+    time: Res<Time>,
+) {
+    for (mut visibility, mut prop) in &mut props {
+        if !prop.broken {
+            continue;
+        }
+        let Some(respawn) = &mut prop.respawn else {
+            continue;
+        };
+
+        respawn.tick(time.delta());
+        if respawn.just_finished() {
+            broken.0.remove(&prop.id);
+            prop.broken = false;
+            prop.health = prop.max_health;
+            *visibility = Visibility::Inherited;
+        }
+    }
+}
@@ -0,0 +1,320 @@
+use std::{fs, time::Duration};
+
+use bevy::{ecs::system::SystemParam, window::WindowCloseRequested};
+
+use crate::{
+    attack::{
+        area_shot_targeting, close_shot_targeting, spawn_area_shot, spawn_close_shot, spawn_whip,
+    },
+    enemy::spawn_enemy_entity,
+    exp::spawn_exp_orb,
+    prelude::*,
+    prop::spawn_pickup,
+    storage::{data_file, load_ron, save_ron},
+};
+
+const RUN_FILE: &str = "run.ron";
+
+pub struct SavePlugin;
+
+impl Plugin for SavePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<PendingResume>()
+            .add_system(save_run_on_close)
+            .add_system(continue_button_system)
+            .add_system(discard_run_save.in_schedule(OnEnter(GameState::StartingLoop)))
+            .add_system(resume_run.in_schedule(OnEnter(GameState::Gameplay)))
+            //A finished run can't be continued
+            .add_system(delete_run_save.in_schedule(OnEnter(GameState::GameOver)));
+    }
+}
+
+pub fn run_save_exists() -> bool {
+    data_file(RUN_FILE).is_some_and(|path| path.exists())
+}
+
+fn delete_run_save() {
+    if let Some(path) = data_file(RUN_FILE) {
+        //Usually there is nothing to remove
+        let _ = fs::remove_file(path);
+    }
+}
+
+//Only one run can be saved, starting a new one gives up the old
+fn discard_run_save(pending: Res<PendingResume>) {
+    if pending.0.is_none() {
+        delete_run_save();
+    }
+}
+
+/// Run wide resources that go into a save
+#[derive(SystemParam)]
+struct RunProgress<'w> {
+    stage: Res<'w, SelectedStage>,
+    character: Res<'w, SelectedCharacter>,
+    inventory: Res<'w, Inventory>,
+    offers: Res<'w, LevelUpOffers>,
+    wave_manager: Res<'w, WaveManager>,
+    run_clock: Res<'w, RunClock>,
+    broken_props: Res<'w, BrokenProps>,
+}
+
+/// Everything in the world that gets saved besides the player
+#[derive(SystemParam)]
+struct RunEntities<'w, 's> {
+    whips: Query<'w, 's, (&'static Whip, &'static Transform, &'static Sprite)>,
+    //Anything without a close shot is an area shot
+    shots: Query<'w, 's, Option<&'static CloseShot>, Or<(With<CloseShot>, With<AreaShot>)>>,
+    enemies: Query<'w, 's, (&'static Enemy, &'static Transform)>,
+    orbs: Query<'w, 's, (&'static ExpOrb, &'static Transform)>,
+    pickups: Query<'w, 's, (&'static Pickup, &'static Transform)>,
+}
+
+fn save_run_on_close(
+    mut close_events: EventReader<WindowCloseRequested>,
+    state: Res<State<GameState>>,
+    progress: RunProgress,
+    entities: RunEntities,
+    mut rng: ResMut<GlobalRng>,
+    player: Query<(&Transform, &Player, &Dash)>,
+) {
+    if close_events.iter().count() == 0 {
+        return;
+    }
+    let in_run = matches!(
+        state.0,
+        GameState::Gameplay | GameState::Paused | GameState::LevelUp | GameState::Chest
+    );
+    let Ok((transform, player, dash)) = player.get_single() else {
+        return;
+    };
+    if !in_run {
+        return;
+    }
+
+    let mut weapons = entities
+        .whips
+        .iter()
+        .map(|(whip, transform, sprite)| WeaponSnapshot::Whip {
+            damage: whip.damage,
+            translation: transform.translation,
+            flip_x: sprite.flip_x,
+            elapsed: whip.timer.elapsed_secs(),
+        })
+        .collect::<Vec<_>>();
+    weapons.extend(entities.shots.iter().map(|close_shot| match close_shot {
+        Some(_) => WeaponSnapshot::CloseShot,
+        None => WeaponSnapshot::AreaShot,
+    }));
+
+    let position = |transform: &Transform| transform.translation.truncate();
+
+    //Reseed so this session and the resumed one continue from the same sequence
+    let rng_seed = rng.u64(..);
+    *rng = GlobalRng::with_seed(rng_seed);
+
+    let mut snapshot = RunSnapshot {
+        character: progress.character.0,
+        stage: progress.stage.0,
+        position: position(transform),
+        player: player.clone(),
+        dash: dash.clone(),
+        inventory: progress.inventory.clone(),
+        offers: progress.offers.clone(),
+        weapons,
+        wave_time: progress.wave_manager.global_time.elapsed_secs(),
+        elite_time: progress.wave_manager.elite_timer.elapsed_secs(),
+        boss_time: progress.wave_manager.boss_timer.elapsed_secs(),
+        broken_props: progress
+            .broken_props
+            .0
+            .iter()
+            .map(|(id, broken_at)| (*id, *broken_at))
+            .collect(),
+        run_time: progress.run_clock.0.elapsed_secs(),
+        enemies: entities
+            .enemies
+            .iter()
+            .map(|(enemy, transform)| (enemy.clone(), position(transform)))
+            .collect(),
+        orbs: entities
+            .orbs
+            .iter()
+            .map(|(orb, transform)| (orb.value, position(transform)))
+            .collect(),
+        pickups: entities
+            .pickups
+            .iter()
+            .map(|(pickup, transform)| (*pickup, position(transform)))
+            .collect(),
+        pending_chest: state.0 == GameState::Chest,
+        rng_seed,
+    };
+    //The level up screen rerolls when it comes back
+    if state.0 == GameState::LevelUp {
+        snapshot.player.pending_levels += 1;
+    }
+
+    save_ron(data_file(RUN_FILE), &snapshot);
+}
+
+fn continue_button_system(
+    mut interaction_query: Query<
+        (
+            &Interaction,
+            &mut BackgroundColor,
+            &mut Style,
+            &mut Visibility,
+        ),
+        (Changed<Interaction>, With<Button>, With<ContinueButtonUI>),
+    >,
+    about: Res<AboutShown>,
+    controls: Res<ControlsShown>,
+    mut pending: ResMut<PendingResume>,
+    mut stage: ResMut<SelectedStage>,
+    mut character: ResMut<SelectedCharacter>,
+    mut next_state: ResMut<NextState<GameState>>,
+) {
+    if about.0 || controls.0 {
+        return;
+    }
+    for (interaction, mut color, mut style, mut visibility) in &mut interaction_query {
+        match *interaction {
+            Interaction::Clicked => {
+                *color = Color::RED.into();
+                let Some(snapshot) = load_ron::<RunSnapshot>(data_file(RUN_FILE)) else {
+                    //Unreadable save, don't offer it again
+                    delete_run_save();
+                    //Collapsed so the rest of the menu closes the gap
+                    style.display = Display::None;
+                    *visibility = Visibility::Hidden;
+                    continue;
+                };
+                stage.0 = snapshot.stage;
+                character.0 = snapshot.character;
+                pending.0 = Some(snapshot);
+                next_state.set(GameState::StartingLoop);
+            }
+            Interaction::Hovered => {
+                *color = Color::GREEN.into();
+            }
+            Interaction::None => {
+                *color = Color::DARK_GREEN.into();
+            }
+        }
+    }
+}
+
+/// Run wide resources a save gets loaded back into
+#[derive(SystemParam)]
+struct ResumedProgress<'w> {
+    inventory: ResMut<'w, Inventory>,
+    offers: ResMut<'w, LevelUpOffers>,
+    wave_manager: ResMut<'w, WaveManager>,
+    run_clock: ResMut<'w, RunClock>,
+    broken_props: ResMut<'w, BrokenProps>,
+}
+
+//Runs on every return to gameplay but only does anything right after continuing
+fn resume_run(
+    mut commands: Commands,
+    assets: Res<AssetServer>,
+    mut pending: ResMut<PendingResume>,
+    mut progress: ResumedProgress,
+    mut rng: ResMut<GlobalRng>,
+    mut player: Query<(Entity, &mut Transform, &mut Player, &mut Dash)>,
+    weapons: Query<Entity, Or<(With<Whip>, With<CloseShot>, With<AreaShot>)>>,
+) {
+    let Some(snapshot) = pending.0.take() else {
+        return;
+    };
+    //Resuming uses the save up, it gets written again on the next quit
+    delete_run_save();
+
+    let (entity, mut transform, mut player, mut dash) = player.single_mut();
+    transform.translation = snapshot.position.extend(transform.translation.z);
+    *player = snapshot.player;
+    *dash = snapshot.dash;
+    *progress.inventory = snapshot.inventory;
+    *progress.offers = snapshot.offers;
+    *rng = GlobalRng::with_seed(snapshot.rng_seed);
+
+    progress
+        .wave_manager
+        .global_time
+        .set_elapsed(Duration::from_secs_f32(snapshot.wave_time));
+    progress
+        .wave_manager
+        .elite_timer
+        .set_elapsed(Duration::from_secs_f32(snapshot.elite_time));
+    progress
+        .wave_manager
+        .boss_timer
+        .set_elapsed(Duration::from_secs_f32(snapshot.boss_time));
+    progress
+        .run_clock
+        .0
+        .set_elapsed(Duration::from_secs_f32(snapshot.run_time));
+    //The stage just respawned every prop, they get put back as they were on the first update
+    progress.broken_props.0 = snapshot.broken_props.into_iter().collect();
+
+    //Swap the fresh starting weapon for the saved loadout
+    for weapon in &weapons {
+        commands.entity(weapon).despawn_recursive();
+    }
+    let evolved = |upgrade| progress.inventory.level(&upgrade) > 0;
+    for weapon in snapshot.weapons {
+        let weapon = match weapon {
+            WeaponSnapshot::Whip {
+                damage,
+                translation,
+                flip_x,
+                elapsed,
+            } => {
+                let whip = spawn_whip(&mut commands, &assets);
+                commands.entity(whip).add(move |whip, world: &mut World| {
+                    let mut entity = world.entity_mut(whip);
+                    if let Some(mut transform) = entity.get_mut::<Transform>() {
+                        transform.translation = translation;
+                    }
+                    if let Some(mut sprite) = entity.get_mut::<Sprite>() {
+                        sprite.flip_x = flip_x;
+                    }
+                    if let Some(mut state) = entity.get_mut::<Whip>() {
+                        state.damage = damage;
+                        state.timer.set_elapsed(Duration::from_secs_f32(elapsed));
+                    }
+                });
+                whip
+            }
+            WeaponSnapshot::CloseShot => spawn_close_shot(
+                &mut commands,
+                close_shot_targeting(evolved(WeaponUpgrade::BurritoBarrage)),
+            ),
+            WeaponSnapshot::AreaShot => spawn_area_shot(
+                &mut commands,
+                area_shot_targeting(evolved(WeaponUpgrade::NachoAvalanche)),
+            ),
+        };
+        commands.entity(entity).add_child(weapon);
+    }
+
+    for (enemy, position) in snapshot.enemies {
+        spawn_enemy_entity(
+            &mut commands,
+            &assets,
+            &mut rng,
+            enemy,
+            position.extend(100.0),
+        );
+    }
+    for (value, position) in snapshot.orbs {
+        spawn_exp_orb(&mut commands, &assets, value, position);
+    }
+    for (pickup, position) in snapshot.pickups {
+        spawn_pickup(&mut commands, &assets, pickup, position);
+    }
+    if snapshot.pending_chest {
+        spawn_pickup(&mut commands, &assets, Pickup::Chest, snapshot.position);
+    }
+}
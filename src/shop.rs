@@ -0,0 +1,375 @@
+use strum::IntoEnumIterator;
+
+use crate::{
+    prelude::*,
+    storage::{data_file, load_ron, save_ron},
+};
+
+const PROFILE_FILE: &str = "profile.ron";
+
+pub struct ShopPlugin;
+
+impl Plugin for ShopPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(load_profile())
+            .add_system(bank_run_gold.in_schedule(OnEnter(GameState::GameOver)))
+            .add_system(despawn_shop_ui.in_schedule(OnExit(GameState::Shop)))
+            .add_system(shop_button_system)
+            .add_systems(
+                (
+                    show_shop_ui,
+                    buy_button_system,
+                    refund_button_system,
+                    shop_back_button_system,
+                )
+                    .in_set(OnUpdate(GameState::Shop)),
+            );
+    }
+}
+
+impl MetaUpgrade {
+    pub fn name(&self) -> &str {
+        match self {
+            MetaUpgrade::MaxHealth => "Bigger Appetite",
+            MetaUpgrade::Might => "Extra Spicy",
+            MetaUpgrade::Magnet => "Long Arms",
+            MetaUpgrade::Armor => "Thick Crust",
+            MetaUpgrade::Revival => "Second Delivery",
+        }
+    }
+
+    pub fn description(&self) -> &str {
+        match self {
+            MetaUpgrade::MaxHealth => "+10% max health",
+            MetaUpgrade::Might => "+5% damage",
+            MetaUpgrade::Magnet => "+10% pickup radius",
+            MetaUpgrade::Armor => "+1 armor",
+            MetaUpgrade::Revival => "+1 extra life",
+        }
+    }
+
+    pub fn max_level(&self) -> u32 {
+        match self {
+            MetaUpgrade::MaxHealth | MetaUpgrade::Might | MetaUpgrade::Magnet => 5,
+            MetaUpgrade::Armor => 3,
+            MetaUpgrade::Revival => 1,
+        }
+    }
+
+    //Each level costs more than the last
+    pub fn cost(&self, level: u32) -> u32 {
+        let base = match self {
+            MetaUpgrade::MaxHealth => 100,
+            MetaUpgrade::Might => 150,
+            MetaUpgrade::Magnet => 80,
+            MetaUpgrade::Armor => 120,
+            MetaUpgrade::Revival => 500,
+        };
+        base * (level + 1)
+    }
+
+    fn apply(&self, level: u32, player: &mut Player) {
+        let level = level as f32;
+        match self {
+            MetaUpgrade::MaxHealth => {
+                player.max_health *= 1.0 + 0.1 * level;
+                player.health = player.max_health;
+            }
+            MetaUpgrade::Might => player.might *= 1.0 + 0.05 * level,
+            MetaUpgrade::Magnet => player.magnet *= 1.0 + 0.1 * level,
+            MetaUpgrade::Armor => player.armor += level,
+            MetaUpgrade::Revival => player.revives += level as u32,
+        }
+    }
+}
+
+impl Profile {
+    pub fn level(&self, upgrade: MetaUpgrade) -> u32 {
+        self.purchased
+            .iter()
+            .find(|(owned, _)| *owned == upgrade)
+            .map_or(0, |(_, level)| *level)
+    }
+
+    /// Permanent bonuses for a freshly spawned player
+    pub fn apply(&self, player: &mut Player) {
+        for (upgrade, level) in &self.purchased {
+            upgrade.apply(*level, player);
+        }
+    }
+
+    fn buy(&mut self, upgrade: MetaUpgrade) -> bool {
+        let level = self.level(upgrade);
+        let cost = upgrade.cost(level);
+        if level >= upgrade.max_level() || self.gold < cost {
+            return false;
+        }
+        self.gold -= cost;
+        match self
+            .purchased
+            .iter_mut()
+            .find(|(owned, _)| *owned == upgrade)
+        {
+            Some((_, level)) => *level += 1,
+            None => self.purchased.push((upgrade, 1)),
+        }
+        true
+    }
+
+    //Everything back at full price
+    fn refund(&mut self) {
+        for (upgrade, level) in self.purchased.drain(..) {
+            self.gold += (0..level).map(|level| upgrade.cost(level)).sum::<u32>();
+        }
+    }
+}
+
+fn load_profile() -> Profile {
+    load_ron(data_file(PROFILE_FILE)).unwrap_or_default()
+}
+
+fn save_profile(profile: &Profile) {
+    save_ron(data_file(PROFILE_FILE), profile);
+}
+
+fn bank_run_gold(mut profile: ResMut<Profile>, player: Query<&Player>) {
+    let Ok(player) = player.get_single() else {
+        return;
+    };
+    profile.gold += player.gold;
+    save_profile(&profile);
+}
+
+fn shop_button_system(
+    mut interaction_query: Query<
+        (&Interaction, &mut BackgroundColor),
+        (Changed<Interaction>, With<Button>, With<ShopButtonUI>),
+    >,
+    about: Res<AboutShown>,
+    controls: Res<ControlsShown>,
+    mut next_state: ResMut<NextState<GameState>>,
+) {
+    if about.0 || controls.0 {
+        return;
+    }
+    for (interaction, mut color) in &mut interaction_query {
+        match *interaction {
+            Interaction::Clicked => {
+                *color = Color::RED.into();
+                next_state.set(GameState::Shop);
+            }
+            Interaction::Hovered => {
+                *color = Color::GREEN.into();
+            }
+            Interaction::None => {
+                *color = Color::CRIMSON.into();
+            }
+        }
+    }
+}
+
+fn buy_button_system(
+    mut interaction_query: Query<
+        (&Interaction, &mut BackgroundColor, &ShopBuyButtonUI),
+        (Changed<Interaction>, With<Button>),
+    >,
+    mut profile: ResMut<Profile>,
+) {
+    for (interaction, mut color, upgrade) in &mut interaction_query {
+        match *interaction {
+            Interaction::Clicked => {
+                *color = Color::RED.into();
+                if profile.buy(upgrade.0) {
+                    save_profile(&profile);
+                }
+            }
+            Interaction::Hovered => {
+                *color = Color::GREEN.into();
+            }
+            Interaction::None => {
+                *color = Color::DARK_GREEN.into();
+            }
+        }
+    }
+}
+
+fn refund_button_system(
+    mut interaction_query: Query<
+        (&Interaction, &mut BackgroundColor),
+        (Changed<Interaction>, With<Button>, With<ShopRefundButtonUI>),
+    >,
+    mut profile: ResMut<Profile>,
+) {
+    for (interaction, mut color) in &mut interaction_query {
+        match *interaction {
+            Interaction::Clicked => {
+                *color = Color::RED.into();
+                if !profile.purchased.is_empty() {
+                    profile.refund();
+                    save_profile(&profile);
+                }
+            }
+            Interaction::Hovered => {
+                *color = Color::GREEN.into();
+            }
+            Interaction::None => {
+                *color = Color::CRIMSON.into();
+            }
+        }
+    }
+}
+
+fn shop_back_button_system(
+    mut interaction_query: Query<
+        (&Interaction, &mut BackgroundColor),
+        (Changed<Interaction>, With<Button>, With<ShopBackButtonUI>),
+    >,
+    mut next_state: ResMut<NextState<GameState>>,
+) {
+    for (interaction, mut color) in &mut interaction_query {
+        match *interaction {
+            Interaction::Clicked => {
+                *color = Color::RED.into();
+                next_state.set(GameState::MainMenu);
+            }
+            Interaction::Hovered => {
+                *color = Color::GREEN.into();
+            }
+            Interaction::None => {
+                *color = Color::CRIMSON.into();
+            }
+        }
+    }
+}
+
+//Rebuilt after every purchase so the costs and gold stay current
+fn show_shop_ui(
+    mut commands: Commands,
+    assets: Res<AssetServer>,
+    profile: Res<Profile>,
+    ui: Query<Entity, With<ShopUI>>,
+) {
+    if !profile.is_changed() && !ui.is_empty() {
+        return;
+    }
+    for ui in &ui {
+        commands.entity(ui).despawn_recursive();
+    }
+    spawn_shop_ui(&mut commands, &assets, &profile);
+}
+
+fn spawn_shop_ui(commands: &mut Commands, assets: &AssetServer, profile: &Profile) {
+    let font = assets.load("fonts/pointfree.ttf");
+    let text_style = |font_size: f32| TextStyle {
+        font: font.clone(),
+        font_size,
+        color: Color::rgb(0.9, 0.9, 0.9),
+    };
+
+    let shop_parent = (
+        NodeBundle {
+            style: Style {
+                size: Size::new(Val::Percent(100.0), Val::Percent(100.0)),
+                position_type: PositionType::Absolute,
+                align_items: AlignItems::Center,
+                justify_content: JustifyContent::Center,
+                ..default()
+            },
+            ..default()
+        },
+        ShopUI,
+        Name::new("Shop UI"),
+    );
+
+    let shop_box = NodeBundle {
+        style: Style {
+            size: Size::new(Val::Percent(85.0), Val::Percent(90.0)),
+            position_type: PositionType::Relative,
+            align_items: AlignItems::Center,
+            flex_direction: FlexDirection::Column,
+            justify_content: JustifyContent::SpaceAround,
+            ..default()
+        },
+        background_color: Color::DARK_GRAY.into(),
+        ..default()
+    };
+
+    let row = |height: f32| NodeBundle {
+        style: Style {
+            size: Size::new(Val::Percent(90.0), Val::Percent(height)),
+            flex_direction: FlexDirection::Row,
+            justify_content: JustifyContent::SpaceBetween,
+            align_items: AlignItems::Center,
+            ..default()
+        },
+        ..default()
+    };
+
+    let button = |width: f32, color: Color| ButtonBundle {
+        style: Style {
+            size: Size::new(Val::Percent(width), Val::Percent(100.0)),
+            justify_content: JustifyContent::Center,
+            align_items: AlignItems::Center,
+            ..default()
+        },
+        background_color: color.into(),
+        ..default()
+    };
+
+    commands.spawn(shop_parent).with_children(|commands| {
+        commands.spawn(shop_box).with_children(|commands| {
+            commands.spawn(TextBundle::from_section("Shop", text_style(48.0)));
+            commands.spawn(TextBundle::from_section(
+                format!("Gold: {}", profile.gold),
+                TextStyle {
+                    color: Color::GOLD,
+                    ..text_style(32.0)
+                },
+            ));
+
+            for upgrade in MetaUpgrade::iter() {
+                let level = profile.level(upgrade);
+                let price = if level >= upgrade.max_level() {
+                    "Maxed".to_string()
+                } else {
+                    format!("Buy {}g", upgrade.cost(level))
+                };
+
+                commands.spawn(row(10.0)).with_children(|commands| {
+                    commands.spawn(TextBundle::from_section(
+                        format!("{} - {}", upgrade.name(), upgrade.description()),
+                        text_style(24.0),
+                    ));
+                    commands.spawn(TextBundle::from_section(
+                        format!("Lv {}/{}", level, upgrade.max_level()),
+                        text_style(24.0),
+                    ));
+                    commands
+                        .spawn((button(25.0, Color::DARK_GREEN), ShopBuyButtonUI(upgrade)))
+                        .with_children(|commands| {
+                            commands.spawn(TextBundle::from_section(price, text_style(24.0)));
+                        });
+                });
+            }
+
+            commands.spawn(row(10.0)).with_children(|commands| {
+                commands
+                    .spawn((button(40.0, Color::CRIMSON), ShopRefundButtonUI))
+                    .with_children(|commands| {
+                        commands.spawn(TextBundle::from_section("Refund All", text_style(28.0)));
+                    });
+                commands
+                    .spawn((button(40.0, Color::CRIMSON), ShopBackButtonUI))
+                    .with_children(|commands| {
+                        commands.spawn(TextBundle::from_section("Back", text_style(28.0)));
+                    });
+            });
+        });
+    });
+}
+
+fn despawn_shop_ui(mut commands: Commands, ui: Query<Entity, With<ShopUI>>) {
+    for ui in &ui {
+        commands.entity(ui).despawn_recursive();
+    }
+}
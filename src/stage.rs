@@ -0,0 +1,330 @@
+use bevy::time::Stopwatch;
+
+use crate::{
+    enemy::{BOSS_INTERVAL, ELITE_INTERVAL},
+    prelude::*,
+    prop::spawn_prop,
+    storage::parse_baked,
+};
+
+const TILE_SIZE: f32 = 1080.0 * PIXEL_TO_WORLD;
+//Chunks kept loaded in each direction around the camera
+const CHUNK_RADIUS: i32 = 2;
+
+pub struct StagePlugin;
+
+impl Plugin for StagePlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(load_stages())
+            .init_resource::<SelectedStage>()
+            .init_resource::<Music>()
+            .insert_resource(WaveManager {
+                global_time: Stopwatch::new(),
+                waves: Vec::new(),
+                elite_timer: Timer::from_seconds(ELITE_INTERVAL, TimerMode::Repeating),
+                boss_timer: Timer::from_seconds(BOSS_INTERVAL, TimerMode::Repeating),
+            })
+            .init_resource::<LoadedChunks>()
+            .add_startup_system(start_menu_music)
+            .add_systems(
+                (spawn_stage, load_stage_waves, play_stage_music)
+                    .in_schedule(OnEnter(GameState::StartingLoop)),
+            )
+            .add_system(stream_chunks.in_set(OnUpdate(GameState::Gameplay)));
+    }
+}
+
+fn load_stages() -> Stages {
+    let stages: Vec<Stage> = parse_baked("stages.ron", include_str!("../assets/stages.ron"));
+    assert!(!stages.is_empty(), "Need at least one stage");
+    Stages(stages)
+}
+
+impl StageBounds {
+    pub fn name(&self) -> &str {
+        match self {
+            StageBounds::Open => "Open",
+            StageBounds::Endless => "Endless",
+            StageBounds::Walled(_) => "Walled",
+        }
+    }
+}
+
+pub fn selected_stage<'a>(stages: &'a Stages, selected: &SelectedStage) -> &'a Stage {
+    stages.0.get(selected.0).unwrap_or(&stages.0[0])
+}
+
+fn spawn_stage(
+    mut commands: Commands,
+    assets: Res<AssetServer>,
+    stages: Res<Stages>,
+    selected: Res<SelectedStage>,
+    mut chunks: ResMut<LoadedChunks>,
+) {
+    let stage = selected_stage(&stages, &selected);
+    //Last run's chunks were despawned with the rest of the gameplay entities
+    chunks.0.clear();
+
+    for i in -stage.tiles..stage.tiles {
+        for j in -stage.tiles..stage.tiles {
+            commands.spawn((
+                SpriteBundle {
+                    transform: Transform::from_xyz(i as f32 * TILE_SIZE, j as f32 * TILE_SIZE, 0.0),
+                    sprite: Sprite {
+                        custom_size: Some(Vec2::new(TILE_SIZE, TILE_SIZE)),
+                        color: stage.tile_tint,
+                        ..default()
+                    },
+                    texture: assets.load(&stage.tile),
+                    ..default()
+                },
+                GamePlayEntity,
+                Name::new("Background"),
+            ));
+        }
+    }
+
+    if let StageBounds::Walled(half_size) = stage.bounds {
+        spawn_walls(&mut commands, half_size);
+    }
+
+    for obstacle in &stage.obstacles {
+        commands.spawn((
+            SpriteBundle {
+                transform: Transform::from_translation(obstacle.position.extend(50.0)),
+                sprite: Sprite {
+                    custom_size: Some(obstacle.size),
+                    color: obstacle.color,
+                    ..default()
+                },
+                ..default()
+            },
+            RigidBody::Fixed,
+            Collider::cuboid(obstacle.size.x / 2.0, obstacle.size.y / 2.0),
+            //Unlike the arena walls these are inside the play area so enemies bump into them too
+            CollisionGroups::new(WALL_GROUP, PLAYER_GROUP | ENEMY_GROUP),
+            GamePlayEntity,
+            Name::new("Obstacle"),
+        ));
+    }
+}
+
+fn spawn_walls(commands: &mut Commands, half_size: f32) {
+    let thickness = 5.0;
+
+    for (position, half_extents) in [
+        (
+            Vec2::new(0.0, half_size + thickness),
+            Vec2::new(half_size + 2.0 * thickness, thickness),
+        ),
+        (
+            Vec2::new(0.0, -half_size - thickness),
+            Vec2::new(half_size + 2.0 * thickness, thickness),
+        ),
+        (
+            Vec2::new(half_size + thickness, 0.0),
+            Vec2::new(thickness, half_size),
+        ),
+        (
+            Vec2::new(-half_size - thickness, 0.0),
+            Vec2::new(thickness, half_size),
+        ),
+    ] {
+        commands.spawn((
+            TransformBundle::from(Transform::from_translation(position.extend(0.0))),
+            RigidBody::Fixed,
+            Collider::cuboid(half_extents.x, half_extents.y),
+            CollisionGroups::new(WALL_GROUP, PLAYER_GROUP),
+            GamePlayEntity,
+            Name::new("Wall"),
+        ));
+    }
+}
+
+fn load_stage_waves(
+    mut wave_manager: ResMut<WaveManager>,
+    stages: Res<Stages>,
+    selected: Res<SelectedStage>,
+) {
+    let stage = selected_stage(&stages, &selected);
+
+    wave_manager.waves = stage
+        .waves
+        .iter()
+        .map(|wave| Wave {
+            next_spawn: Timer::from_seconds(wave.spawn_interval, TimerMode::Repeating),
+            wave_size: wave.wave_size,
+            to_spawn: wave.enemy.clone(),
+        })
+        .collect();
+    wave_manager.elite_timer = Timer::from_seconds(ELITE_INTERVAL, TimerMode::Repeating);
+    wave_manager.boss_timer = Timer::from_seconds(BOSS_INTERVAL, TimerMode::Repeating);
+}
+
+fn play_music(
+    audio: &Audio,
+    assets: &AssetServer,
+    sinks: &Assets<AudioSink>,
+    music: &mut Music,
+    track: &str,
+) {
+    //Keep the song going if the next stage uses the same one
+    if music.track == track {
+        return;
+    }
+    if let Some(sink) = sinks.get(&music.sink) {
+        sink.stop();
+    }
+
+    let sink = audio.play_with_settings(
+        assets.load(track),
+        PlaybackSettings {
+            repeat: true,
+            volume: 0.5,
+            speed: 1.0,
+        },
+    );
+    music.track = track.to_string();
+    music.sink = sinks.get_handle(sink);
+}
+
+fn start_menu_music(
+    audio: Res<Audio>,
+    assets: Res<AssetServer>,
+    sinks: Res<Assets<AudioSink>>,
+    mut music: ResMut<Music>,
+) {
+    play_music(&audio, &assets, &sinks, &mut music, "background.wav");
+}
+
+fn play_stage_music(
+    audio: Res<Audio>,
+    assets: Res<AssetServer>,
+    sinks: Res<Assets<AudioSink>>,
+    mut music: ResMut<Music>,
+    stages: Res<Stages>,
+    selected: Res<SelectedStage>,
+) {
+    let stage = selected_stage(&stages, &selected);
+    play_music(&audio, &assets, &sinks, &mut music, &stage.music);
+}
+
+//splitmix64, cheap and good enough to scatter decorations
+fn hash(mut x: u64) -> u64 {
+    x = x.wrapping_add(0x9e3779b97f4a7c15);
+    x = (x ^ (x >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+    x = (x ^ (x >> 27)).wrapping_mul(0x94d049bb133111eb);
+    x ^ (x >> 31)
+}
+
+fn chunk_hash(seed: u64, chunk: IVec2, index: u32) -> u64 {
+    let mut x = hash(seed);
+    x = hash(x ^ chunk.x as u32 as u64);
+    x = hash(x ^ chunk.y as u32 as u64);
+    hash(x ^ index as u64)
+}
+
+//Top 24 bits as a float in 0..1
+fn unit_float(x: u64) -> f32 {
+    (x >> 40) as f32 / (1u64 << 24) as f32
+}
+
+fn spawn_chunk(
+    commands: &mut Commands,
+    assets: &AssetServer,
+    stage: &Stage,
+    chunk: IVec2,
+) -> Entity {
+    let chunk_entity = commands
+        .spawn((
+            SpriteBundle {
+                transform: Transform::from_translation((chunk.as_vec2() * TILE_SIZE).extend(0.0)),
+                sprite: Sprite {
+                    custom_size: Some(Vec2::new(TILE_SIZE, TILE_SIZE)),
+                    color: stage.tile_tint,
+                    ..default()
+                },
+                texture: assets.load(&stage.tile),
+                ..default()
+            },
+            GamePlayEntity,
+            Name::new("Chunk"),
+        ))
+        .with_children(|commands| {
+            if stage.decorations.is_empty() {
+                return;
+            }
+            //Same seed and chunk always gives the same layout
+            for index in 0..stage.decorations_per_chunk {
+                let roll = chunk_hash(stage.seed, chunk, index);
+                let position =
+                    Vec2::new(unit_float(roll) - 0.5, unit_float(hash(roll)) - 0.5) * TILE_SIZE;
+                let decoration = &stage.decorations
+                    [(hash(hash(roll)) % stage.decorations.len() as u64) as usize];
+                commands.spawn((
+                    SpriteBundle {
+                        transform: Transform::from_translation(position.extend(1.0)),
+                        sprite: Sprite {
+                            custom_size: Some(Vec2::splat(0.8)),
+                            flip_x: roll & 1 == 0,
+                            ..default()
+                        },
+                        texture: assets.load(decoration),
+                        ..default()
+                    },
+                    Name::new("Decoration"),
+                ));
+            }
+        })
+        .id();
+
+    for (index, prop) in stage.props.iter().enumerate() {
+        let id = PropId {
+            chunk: Some(chunk),
+            index,
+        };
+        let prop = spawn_prop(commands, assets, prop, id, stage.prop_respawn);
+        commands.entity(chunk_entity).add_child(prop);
+    }
+
+    chunk_entity
+}
+
+fn stream_chunks(
+    mut commands: Commands,
+    assets: Res<AssetServer>,
+    stages: Res<Stages>,
+    selected: Res<SelectedStage>,
+    camera: Query<&Transform, With<MainCamera>>,
+    mut chunks: ResMut<LoadedChunks>,
+) {
+    let stage = selected_stage(&stages, &selected);
+    if !matches!(stage.bounds, StageBounds::Endless) {
+        return;
+    }
+
+    let camera = camera.single();
+    let center = (camera.translation.truncate() / TILE_SIZE)
+        .round()
+        .as_ivec2();
+
+    for x in -CHUNK_RADIUS..=CHUNK_RADIUS {
+        for y in -CHUNK_RADIUS..=CHUNK_RADIUS {
+            let chunk = center + IVec2::new(x, y);
+            if !chunks.0.contains_key(&chunk) {
+                let entity = spawn_chunk(&mut commands, &assets, stage, chunk);
+                chunks.0.insert(chunk, entity);
+            }
+        }
+    }
+
+    //One chunk of slack so walking along a border doesn't thrash
+    chunks.0.retain(|chunk, entity| {
+        let offset = (*chunk - center).abs();
+        let keep = offset.x <= CHUNK_RADIUS + 1 && offset.y <= CHUNK_RADIUS + 1;
+        if !keep {
+            commands.entity(*entity).despawn_recursive();
+        }
+        keep
+    });
+}
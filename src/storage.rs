@@ -0,0 +1,73 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use ron::ser::PrettyConfig;
+use serde::{de::DeserializeOwned, Serialize};
+
+use crate::prelude::*;
+
+const GAME_DIR: &str = "pizza_survivor";
+
+/// Settings the player might want to hand edit
+pub fn config_file(name: &str) -> Option<PathBuf> {
+    dirs::config_dir().map(|dir| dir.join(GAME_DIR).join(name))
+}
+
+/// Progress the game writes on its own
+pub fn data_file(name: &str) -> Option<PathBuf> {
+    dirs::data_dir().map(|dir| dir.join(GAME_DIR).join(name))
+}
+
+//Tuning data is baked into the binary with include_str so wasm builds don't need to fetch it
+pub fn parse_baked<T: DeserializeOwned>(name: &str, text: &str) -> T {
+    ron::from_str(text).unwrap_or_else(|err| panic!("assets/{} should be valid: {}", name, err))
+}
+
+pub fn load_ron<T: DeserializeOwned>(path: Option<PathBuf>) -> Option<T> {
+    let path = path?;
+    //A missing file just means it hasn't been saved yet
+    let text = fs::read_to_string(&path).ok()?;
+
+    match ron::from_str(&text) {
+        Ok(value) => Some(value),
+        Err(err) => {
+            warn!("Failed to parse {}: {}", path.display(), err);
+            None
+        }
+    }
+}
+
+/// Moves an unreadable file aside instead of letting the next save write over it
+pub fn backup_file(path: &Path) {
+    let backup = path.with_extension("ron.bak");
+    if let Err(err) = fs::rename(path, &backup) {
+        warn!("Failed to back up {}: {}", path.display(), err);
+    }
+}
+
+pub fn save_ron<T: Serialize>(path: Option<PathBuf>, value: &T) {
+    let Some(path) = path else {
+        return;
+    };
+
+    let text = match ron::ser::to_string_pretty(value, PrettyConfig::default()) {
+        Ok(text) => text,
+        Err(err) => {
+            warn!("Failed to serialize {}: {}", path.display(), err);
+            return;
+        }
+    };
+
+    if let Some(parent) = path.parent() {
+        if let Err(err) = fs::create_dir_all(parent) {
+            warn!("Failed to create {}: {}", parent.display(), err);
+            return;
+        }
+    }
+
+    if let Err(err) = fs::write(&path, text) {
+        warn!("Failed to write {}: {}", path.display(), err);
+    }
+}
@@ -0,0 +1,219 @@
+use std::cmp::Reverse;
+
+use bevy::utils::FloatOrd;
+
+use crate::prelude::*;
+
+//Half of what the main camera shows, FixedVertical(20.0) at 16:9
+const VIEW_HALF_SIZE: Vec2 = Vec2::new(10.0 * 16.0 / 9.0, 10.0);
+const CLUSTER_RADIUS: f32 = 1.5;
+
+pub struct TargetCandidate {
+    pub position: Vec2,
+    pub health: f32,
+}
+
+pub fn on_screen(position: Vec2, camera_position: Vec2) -> bool {
+    let offset = (position - camera_position).abs();
+    offset.x <= VIEW_HALF_SIZE.x && offset.y <= VIEW_HALF_SIZE.y
+}
+
+/// Picks the world position a weapon at `origin` should fire towards, earlier candidates win ties
+pub fn pick_target(
+    strategy: TargetingStrategy,
+    origin: Vec2,
+    camera_position: Vec2,
+    candidates: &[TargetCandidate],
+    rng: &mut RngComponent,
+) -> Option<Vec2> {
+    let visible = || {
+        candidates
+            .iter()
+            .filter(move |candidate| on_screen(candidate.position, camera_position))
+    };
+
+    match strategy {
+        TargetingStrategy::Nearest => candidates
+            .iter()
+            .min_by_key(|candidate| FloatOrd(Vec2::distance(origin, candidate.position)))
+            .map(|candidate| candidate.position),
+        TargetingStrategy::RandomOnScreen => {
+            let visible = visible().collect::<Vec<_>>();
+            rng.sample(&visible).map(|candidate| candidate.position)
+        }
+        TargetingStrategy::HighestHealth => visible()
+            .min_by_key(|candidate| Reverse(FloatOrd(candidate.health)))
+            .map(|candidate| candidate.position),
+        TargetingStrategy::DensestCluster => {
+            let visible = visible().collect::<Vec<_>>();
+            visible
+                .iter()
+                .map(|center| {
+                    let neighbors = visible
+                        .iter()
+                        .filter(|other| {
+                            Vec2::distance(center.position, other.position) < CLUSTER_RADIUS
+                        })
+                        .collect::<Vec<_>>();
+                    let centroid = neighbors
+                        .iter()
+                        .fold(Vec2::ZERO, |sum, other| sum + other.position)
+                        / neighbors.len() as f32;
+                    (neighbors.len(), centroid)
+                })
+                .min_by_key(|(count, _)| Reverse(*count))
+                .map(|(_, centroid)| centroid)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn candidate(x: f32, y: f32, health: f32) -> TargetCandidate {
+        TargetCandidate {
+            position: Vec2::new(x, y),
+            health,
+        }
+    }
+
+    fn pick(strategy: TargetingStrategy, candidates: &[TargetCandidate]) -> Option<Vec2> {
+        pick_target(
+            strategy,
+            Vec2::ZERO,
+            Vec2::ZERO,
+            candidates,
+            &mut RngComponent::new(),
+        )
+    }
+
+    const STRATEGIES: [TargetingStrategy; 4] = [
+        TargetingStrategy::Nearest,
+        TargetingStrategy::RandomOnScreen,
+        TargetingStrategy::HighestHealth,
+        TargetingStrategy::DensestCluster,
+    ];
+
+    #[test]
+    fn no_candidates_no_target() {
+        for strategy in STRATEGIES {
+            assert_eq!(pick(strategy, &[]), None, "{:?}", strategy);
+        }
+    }
+
+    #[test]
+    fn nearest_picks_closest() {
+        let candidates = [candidate(5.0, 0.0, 1.0), candidate(0.0, -2.0, 1.0)];
+        assert_eq!(
+            pick(TargetingStrategy::Nearest, &candidates),
+            Some(Vec2::new(0.0, -2.0))
+        );
+    }
+
+    #[test]
+    fn nearest_includes_off_screen() {
+        let candidates = [candidate(50.0, 0.0, 1.0)];
+        assert_eq!(
+            pick(TargetingStrategy::Nearest, &candidates),
+            Some(Vec2::new(50.0, 0.0))
+        );
+    }
+
+    #[test]
+    fn nearest_tie_picks_first() {
+        let candidates = [candidate(3.0, 0.0, 1.0), candidate(-3.0, 0.0, 1.0)];
+        assert_eq!(
+            pick(TargetingStrategy::Nearest, &candidates),
+            Some(Vec2::new(3.0, 0.0))
+        );
+    }
+
+    #[test]
+    fn random_on_screen_skips_off_screen() {
+        let candidates = [
+            candidate(50.0, 0.0, 1.0),
+            candidate(1.0, 1.0, 1.0),
+            candidate(0.0, 30.0, 1.0),
+        ];
+        for _ in 0..20 {
+            assert_eq!(
+                pick(TargetingStrategy::RandomOnScreen, &candidates),
+                Some(Vec2::new(1.0, 1.0))
+            );
+        }
+    }
+
+    #[test]
+    fn random_on_screen_nothing_visible() {
+        let candidates = [candidate(50.0, 0.0, 1.0)];
+        assert_eq!(pick(TargetingStrategy::RandomOnScreen, &candidates), None);
+    }
+
+    #[test]
+    fn random_on_screen_picks_a_visible_candidate() {
+        let candidates = [candidate(1.0, 0.0, 1.0), candidate(-1.0, 0.0, 1.0)];
+        for _ in 0..20 {
+            let target = pick(TargetingStrategy::RandomOnScreen, &candidates).unwrap();
+            assert!(candidates.iter().any(|c| c.position == target));
+        }
+    }
+
+    #[test]
+    fn highest_health_picks_toughest_on_screen() {
+        let candidates = [
+            candidate(1.0, 0.0, 5.0),
+            candidate(2.0, 0.0, 20.0),
+            candidate(50.0, 0.0, 100.0),
+        ];
+        assert_eq!(
+            pick(TargetingStrategy::HighestHealth, &candidates),
+            Some(Vec2::new(2.0, 0.0))
+        );
+    }
+
+    #[test]
+    fn highest_health_tie_picks_first() {
+        let candidates = [candidate(1.0, 0.0, 10.0), candidate(2.0, 0.0, 10.0)];
+        assert_eq!(
+            pick(TargetingStrategy::HighestHealth, &candidates),
+            Some(Vec2::new(1.0, 0.0))
+        );
+    }
+
+    #[test]
+    fn densest_cluster_aims_at_centroid() {
+        let candidates = [
+            candidate(-5.0, 0.0, 1.0),
+            candidate(4.0, 0.0, 1.0),
+            candidate(5.0, 0.0, 1.0),
+            candidate(6.0, 0.0, 1.0),
+        ];
+        assert_eq!(
+            pick(TargetingStrategy::DensestCluster, &candidates),
+            Some(Vec2::new(5.0, 0.0))
+        );
+    }
+
+    #[test]
+    fn densest_cluster_ignores_off_screen() {
+        let candidates = [
+            candidate(2.0, 0.0, 1.0),
+            candidate(50.0, 0.0, 1.0),
+            candidate(50.5, 0.0, 1.0),
+        ];
+        assert_eq!(
+            pick(TargetingStrategy::DensestCluster, &candidates),
+            Some(Vec2::new(2.0, 0.0))
+        );
+    }
+
+    #[test]
+    fn densest_cluster_tie_picks_first() {
+        let candidates = [candidate(-5.0, 0.0, 1.0), candidate(5.0, 0.0, 1.0)];
+        assert_eq!(
+            pick(TargetingStrategy::DensestCluster, &candidates),
+            Some(Vec2::new(-5.0, 0.0))
+        );
+    }
+}
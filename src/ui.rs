@@ -1,4 +1,9 @@
-use crate::prelude::*;
+use bevy::ui::FocusPolicy;
+
+use crate::{
+    input::save_bindings, prelude::*, save::run_save_exists, stage::selected_stage,
+    upgrade::roll_offers,
+};
 
 pub struct GameUiPlugin;
 
@@ -6,23 +11,49 @@ impl Plugin for GameUiPlugin {
     fn build(&self, app: &mut App) {
         app.add_system(spawn_header_ui.in_schedule(OnEnter(GameState::StartingLoop)))
             .add_system(spawn_player_ui.in_schedule(OnEnter(GameState::StartingLoop)))
-            .add_system(spawn_level_up_ui.in_schedule(OnEnter(GameState::LevelUp)))
+            .add_system(reset_health_flash.in_schedule(OnEnter(GameState::StartingLoop)))
+            .add_system(show_level_up_ui.in_set(OnUpdate(GameState::LevelUp)))
             .add_system(despawn_level_up_ui.in_schedule(OnExit(GameState::LevelUp)))
             .add_system(spawn_main_menu_ui.in_schedule(OnEnter(GameState::MainMenu)))
             .add_system(despawn_main_menu_ui.in_schedule(OnExit(GameState::MainMenu)))
+            .add_system(spawn_stage_select_ui.in_schedule(OnEnter(GameState::StageSelect)))
+            .add_system(despawn_stage_select_ui.in_schedule(OnExit(GameState::StageSelect)))
+            .add_system(spawn_character_select_ui.in_schedule(OnEnter(GameState::CharacterSelect)))
+            .add_system(despawn_character_select_ui.in_schedule(OnExit(GameState::CharacterSelect)))
             .add_system(spawn_game_over_ui.in_schedule(OnEnter(GameState::GameOver)))
             .add_system(despawn_game_over_ui.in_schedule(OnExit(GameState::GameOver)))
             .add_system(level_up_button_system)
+            .add_system(reroll_button_system)
+            .add_system(skip_button_system)
+            .add_system(banish_button_system)
             .add_system(about_button_system)
             .add_system(about_back_button_system)
             .add_system(show_about_ui)
             .add_startup_system(spawn_about_ui)
             .add_system(start_button_system)
+            .add_system(aim_mode_button_system)
+            .add_system(controls_button_system)
+            .add_system(controls_back_button_system)
+            .add_system(controls_reset_button_system)
+            .add_system(binding_button_system)
+            .add_system(show_controls_ui)
+            .add_system(stage_button_system)
+            .add_system(stage_card_button_system)
+            .add_system(stage_back_button_system)
+            .add_system(character_button_system)
+            .add_system(character_back_button_system)
             .add_system(game_over_button_system)
             .add_system(update_world_text)
             .insert_resource(AboutShown(false))
+            .insert_resource(ControlsShown(false))
+            .init_resource::<HealthFlash>()
             .add_systems(
-                (player_health_ui_sync, player_exp_ui_sync).in_set(OnUpdate(GameState::Gameplay)),
+                (
+                    player_health_ui_sync,
+                    player_dash_ui_sync,
+                    player_exp_ui_sync,
+                )
+                    .in_set(OnUpdate(GameState::Gameplay)),
             );
     }
 }
@@ -61,7 +92,13 @@ fn update_world_text(
     }
 }
 
-pub fn spawn_world_text(commands: &mut Commands, assets: &AssetServer, position: Vec2, text: &str) {
+pub fn spawn_world_text(
+    commands: &mut Commands,
+    assets: &AssetServer,
+    position: Vec2,
+    text: &str,
+    color: Color,
+) {
     let font = assets.load("fonts/pointfree.ttf");
 
     //Gross offset because text is at top left of given coords
@@ -91,7 +128,7 @@ pub fn spawn_world_text(commands: &mut Commands, assets: &AssetServer, position:
         TextStyle {
             font,
             font_size: 32.0,
-            color: Color::rgb(0.95, 0.2, 0.2),
+            color,
         },
     );
 
@@ -103,15 +140,25 @@ pub fn spawn_world_text(commands: &mut Commands, assets: &AssetServer, position:
 fn level_up_button_system(
     mut interaction_query: Query<
         (&Interaction, &mut BackgroundColor, &WeaponUpgrade),
-        With<Button>,
+        (Changed<Interaction>, With<Button>),
     >,
     mut upgrade_event: EventWriter<UpgradeSelected>,
     mut next_state: ResMut<NextState<GameState>>,
+    mut offers: ResMut<LevelUpOffers>,
+    inventory: Res<Inventory>,
+    player: Query<&Player>,
+    mut rng: ResMut<GlobalRng>,
 ) {
     for (interaction, mut color, weapon) in &mut interaction_query {
         match *interaction {
             Interaction::Clicked => {
                 *color = Color::RED.into();
+                if offers.banishing {
+                    offers.banishes -= 1;
+                    offers.banished.push(weapon.clone());
+                    roll_offers(&mut offers, &inventory, player.single().luck, &mut rng);
+                    return;
+                }
                 next_state.set(GameState::Gameplay);
                 upgrade_event.send(UpgradeSelected(weapon.clone()));
             }
@@ -125,6 +172,89 @@ fn level_up_button_system(
     }
 }
 
+fn reroll_button_system(
+    mut interaction_query: Query<
+        (&Interaction, &mut BackgroundColor),
+        (Changed<Interaction>, With<Button>, With<RerollButtonUI>),
+    >,
+    mut offers: ResMut<LevelUpOffers>,
+    inventory: Res<Inventory>,
+    player: Query<&Player>,
+    mut rng: ResMut<GlobalRng>,
+) {
+    for (interaction, mut color) in &mut interaction_query {
+        match *interaction {
+            Interaction::Clicked => {
+                if offers.rerolls > 0 {
+                    offers.rerolls -= 1;
+                    roll_offers(&mut offers, &inventory, player.single().luck, &mut rng);
+                }
+            }
+            Interaction::Hovered => {
+                *color = Color::GREEN.into();
+            }
+            Interaction::None => {
+                *color = Color::CRIMSON.into();
+            }
+        }
+    }
+}
+
+fn skip_button_system(
+    mut interaction_query: Query<
+        (&Interaction, &mut BackgroundColor),
+        (Changed<Interaction>, With<Button>, With<SkipButtonUI>),
+    >,
+    mut offers: ResMut<LevelUpOffers>,
+    mut player: Query<&mut Player>,
+    mut next_state: ResMut<NextState<GameState>>,
+) {
+    for (interaction, mut color) in &mut interaction_query {
+        match *interaction {
+            Interaction::Clicked => {
+                if offers.skips > 0 {
+                    offers.skips -= 1;
+                    //Small consolation for passing on everything
+                    let mut player = player.single_mut();
+                    player.gold += 10;
+                    player.exp += (player.next_level_exp / 10).max(1);
+                    next_state.set(GameState::Gameplay);
+                }
+            }
+            Interaction::Hovered => {
+                *color = Color::GREEN.into();
+            }
+            Interaction::None => {
+                *color = Color::CRIMSON.into();
+            }
+        }
+    }
+}
+
+fn banish_button_system(
+    mut interaction_query: Query<
+        (&Interaction, &mut BackgroundColor),
+        (Changed<Interaction>, With<Button>, With<BanishButtonUI>),
+    >,
+    mut offers: ResMut<LevelUpOffers>,
+) {
+    for (interaction, mut color) in &mut interaction_query {
+        match *interaction {
+            Interaction::Clicked => {
+                if offers.banishes > 0 {
+                    offers.banishing = !offers.banishing;
+                }
+            }
+            Interaction::Hovered => {
+                *color = Color::GREEN.into();
+            }
+            Interaction::None => {
+                *color = Color::CRIMSON.into();
+            }
+        }
+    }
+}
+
 fn start_button_system(
     mut interaction_query: Query<
         (&Interaction, &mut BackgroundColor),
@@ -140,6 +270,108 @@ fn start_button_system(
         match *interaction {
             Interaction::Clicked => {
                 *color = Color::RED.into();
+                next_state.set(GameState::CharacterSelect);
+            }
+            Interaction::Hovered => {
+                *color = Color::GREEN.into();
+            }
+            Interaction::None => {
+                *color = Color::DARK_GREEN.into();
+            }
+        }
+    }
+}
+
+fn stage_button_system(
+    mut interaction_query: Query<
+        (&Interaction, &mut BackgroundColor),
+        (With<Button>, With<StageButtonUI>),
+    >,
+    about: Res<AboutShown>,
+    controls: Res<ControlsShown>,
+    mut next_state: ResMut<NextState<GameState>>,
+) {
+    if about.0 || controls.0 {
+        return;
+    }
+    for (interaction, mut color) in &mut interaction_query {
+        match *interaction {
+            Interaction::Clicked => {
+                *color = Color::RED.into();
+                next_state.set(GameState::StageSelect);
+            }
+            Interaction::Hovered => {
+                *color = Color::GREEN.into();
+            }
+            Interaction::None => {
+                *color = Color::CRIMSON.into();
+            }
+        }
+    }
+}
+
+fn stage_card_button_system(
+    mut interaction_query: Query<(&Interaction, &mut BackgroundColor, &StageCardUI), With<Button>>,
+    mut selected: ResMut<SelectedStage>,
+    mut next_state: ResMut<NextState<GameState>>,
+) {
+    for (interaction, mut color, stage) in &mut interaction_query {
+        match *interaction {
+            Interaction::Clicked => {
+                *color = Color::RED.into();
+                selected.0 = stage.0;
+                next_state.set(GameState::MainMenu);
+            }
+            Interaction::Hovered => {
+                *color = Color::GREEN.into();
+            }
+            Interaction::None => {
+                *color = if selected.0 == stage.0 {
+                    Color::SEA_GREEN.into()
+                } else {
+                    Color::DARK_GREEN.into()
+                };
+            }
+        }
+    }
+}
+
+fn stage_back_button_system(
+    mut interaction_query: Query<
+        (&Interaction, &mut BackgroundColor),
+        (With<Button>, With<StageBackButton>),
+    >,
+    mut next_state: ResMut<NextState<GameState>>,
+) {
+    for (interaction, mut color) in &mut interaction_query {
+        match *interaction {
+            Interaction::Clicked => {
+                *color = Color::RED.into();
+                next_state.set(GameState::MainMenu);
+            }
+            Interaction::Hovered => {
+                *color = Color::GREEN.into();
+            }
+            Interaction::None => {
+                *color = Color::CRIMSON.into();
+            }
+        }
+    }
+}
+
+fn character_button_system(
+    mut interaction_query: Query<
+        (&Interaction, &mut BackgroundColor, &CharacterButtonUI),
+        With<Button>,
+    >,
+    mut selected: ResMut<SelectedCharacter>,
+    mut next_state: ResMut<NextState<GameState>>,
+) {
+    for (interaction, mut color, character) in &mut interaction_query {
+        match *interaction {
+            Interaction::Clicked => {
+                *color = Color::RED.into();
+                selected.0 = character.0;
                 next_state.set(GameState::StartingLoop);
             }
             Interaction::Hovered => {
@@ -152,6 +384,29 @@ fn start_button_system(
     }
 }
 
+fn character_back_button_system(
+    mut interaction_query: Query<
+        (&Interaction, &mut BackgroundColor),
+        (With<Button>, With<CharacterBackButton>),
+    >,
+    mut next_state: ResMut<NextState<GameState>>,
+) {
+    for (interaction, mut color) in &mut interaction_query {
+        match *interaction {
+            Interaction::Clicked => {
+                *color = Color::RED.into();
+                next_state.set(GameState::MainMenu);
+            }
+            Interaction::Hovered => {
+                *color = Color::GREEN.into();
+            }
+            Interaction::None => {
+                *color = Color::CRIMSON.into();
+            }
+        }
+    }
+}
+
 fn about_button_system(
     mut interaction_query: Query<
         (&Interaction, &mut BackgroundColor),
@@ -179,22 +434,31 @@ fn about_button_system(
     }
 }
 
-fn about_back_button_system(
+fn aim_mode_button_system(
     mut interaction_query: Query<
-        (&Interaction, &mut BackgroundColor),
-        (With<Button>, With<AboutBackButton>),
+        (&Interaction, &mut BackgroundColor, &Children),
+        (Changed<Interaction>, With<Button>, With<AimModeButtonUI>),
     >,
-    mut about: ResMut<AboutShown>,
-    mut next_state: ResMut<NextState<GameState>>,
+    mut text_query: Query<&mut Text>,
+    about: Res<AboutShown>,
+    mut aim_mode: ResMut<AimMode>,
 ) {
-    if !about.0 {
+    if about.0 {
         return;
     }
-    for (interaction, mut color) in &mut interaction_query {
+    for (interaction, mut color, children) in &mut interaction_query {
         match *interaction {
             Interaction::Clicked => {
                 *color = Color::RED.into();
-                about.0 = false;
+                *aim_mode = match *aim_mode {
+                    AimMode::Auto => AimMode::Manual,
+                    AimMode::Manual => AimMode::Auto,
+                };
+                for child in children {
+                    if let Ok(mut text) = text_query.get_mut(*child) {
+                        text.sections[0].value = aim_mode.name().to_string();
+                    }
+                }
             }
             Interaction::Hovered => {
                 *color = Color::GREEN.into();
@@ -206,28 +470,47 @@ fn about_back_button_system(
     }
 }
 
-fn show_about_ui(mut about: Query<&mut Visibility, With<AboutUI>>, about_shown: Res<AboutShown>) {
-    for mut visible in &mut about {
-        if about_shown.0 {
-            *visible = Visibility::Visible;
-        } else {
-            *visible = Visibility::Hidden;
+fn controls_button_system(
+    mut interaction_query: Query<
+        (&Interaction, &mut BackgroundColor),
+        (With<Button>, With<ControlsButtonUI>),
+    >,
+    about: Res<AboutShown>,
+    mut controls: ResMut<ControlsShown>,
+) {
+    if about.0 || controls.0 {
+        return;
+    }
+    for (interaction, mut color) in &mut interaction_query {
+        match *interaction {
+            Interaction::Clicked => {
+                *color = Color::RED.into();
+                controls.0 = true;
+            }
+            Interaction::Hovered => {
+                *color = Color::GREEN.into();
+            }
+            Interaction::None => {
+                *color = Color::DARK_GREEN.into();
+            }
         }
     }
 }
 
-fn game_over_button_system(
+fn controls_back_button_system(
     mut interaction_query: Query<
         (&Interaction, &mut BackgroundColor),
-        (With<Button>, With<GameOverButtonUI>),
+        (With<Button>, With<ControlsBackButton>),
     >,
-    mut next_state: ResMut<NextState<GameState>>,
+    mut controls: ResMut<ControlsShown>,
+    mut rebinding: ResMut<RebindingAction>,
 ) {
     for (interaction, mut color) in &mut interaction_query {
         match *interaction {
             Interaction::Clicked => {
                 *color = Color::RED.into();
-                next_state.set(GameState::MainMenu);
+                controls.0 = false;
+                rebinding.0 = None;
             }
             Interaction::Hovered => {
                 *color = Color::GREEN.into();
@@ -239,30 +522,88 @@ fn game_over_button_system(
     }
 }
 
-fn despawn_level_up_ui(mut commands: Commands, ui: Query<Entity, With<LevelUpUI>>) {
-    for ui in &ui {
-        commands.entity(ui).despawn_recursive();
+fn controls_reset_button_system(
+    mut interaction_query: Query<
+        (&Interaction, &mut BackgroundColor),
+        (
+            Changed<Interaction>,
+            With<Button>,
+            With<ControlsResetButton>,
+        ),
+    >,
+    mut bindings: ResMut<InputBindings>,
+    mut rebinding: ResMut<RebindingAction>,
+) {
+    for (interaction, mut color) in &mut interaction_query {
+        match *interaction {
+            Interaction::Clicked => {
+                *color = Color::RED.into();
+                *bindings = InputBindings::default();
+                save_bindings(&bindings);
+                rebinding.0 = None;
+            }
+            Interaction::Hovered => {
+                *color = Color::GREEN.into();
+            }
+            Interaction::None => {
+                *color = Color::DARK_GREEN.into();
+            }
+        }
     }
 }
 
-fn despawn_main_menu_ui(mut commands: Commands, ui: Query<Entity, With<MainMenuUI>>) {
-    for ui in &ui {
-        commands.entity(ui).despawn_recursive();
+fn binding_button_system(
+    mut interaction_query: Query<
+        (&Interaction, &mut BackgroundColor, &BindingButtonUI),
+        (Changed<Interaction>, With<Button>),
+    >,
+    mut rebinding: ResMut<RebindingAction>,
+) {
+    for (interaction, mut color, binding) in &mut interaction_query {
+        match *interaction {
+            Interaction::Clicked => {
+                *color = Color::RED.into();
+                rebinding.0 = Some(binding.0);
+            }
+            Interaction::Hovered => {
+                *color = Color::GREEN.into();
+            }
+            Interaction::None => {
+                *color = Color::DARK_GREEN.into();
+            }
+        }
     }
 }
 
-fn despawn_game_over_ui(mut commands: Commands, ui: Query<Entity, With<GameOverUI>>) {
+//Rebuilt whenever a binding changes so the labels stay current
+fn show_controls_ui(
+    mut commands: Commands,
+    assets: Res<AssetServer>,
+    controls: Res<ControlsShown>,
+    bindings: Res<InputBindings>,
+    rebinding: Res<RebindingAction>,
+    ui: Query<Entity, With<ControlsUI>>,
+) {
+    if !(controls.is_changed() || bindings.is_changed() || rebinding.is_changed()) {
+        return;
+    }
     for ui in &ui {
         commands.entity(ui).despawn_recursive();
     }
+    if controls.0 {
+        spawn_controls_ui(&mut commands, &assets, &bindings, &rebinding);
+    }
 }
 
-fn spawn_level_up_ui(
-    mut commands: Commands,
-    asset_server: Res<AssetServer>,
-    mut rng: ResMut<GlobalRng>,
+fn spawn_controls_ui(
+    commands: &mut Commands,
+    assets: &AssetServer,
+    bindings: &InputBindings,
+    rebinding: &RebindingAction,
 ) {
-    let level_up_parent = (
+    let font = assets.load("fonts/pointfree.ttf");
+
+    let controls_parent = (
         NodeBundle {
             style: Style {
                 size: Size::new(Val::Percent(100.0), Val::Percent(100.0)),
@@ -271,14 +612,17 @@ fn spawn_level_up_ui(
                 justify_content: JustifyContent::Center,
                 ..default()
             },
+            //Keep clicks off the menu underneath
+            focus_policy: FocusPolicy::Block,
+            z_index: ZIndex::Global(100),
             ..default()
         },
-        LevelUpUI,
+        ControlsUI,
     );
 
-    let level_up_popup = NodeBundle {
+    let controls_box = NodeBundle {
         style: Style {
-            size: Size::new(Val::Percent(60.0), Val::Percent(40.0)),
+            size: Size::new(Val::Percent(95.0), Val::Percent(95.0)),
             position_type: PositionType::Relative,
             align_items: AlignItems::Center,
             flex_direction: FlexDirection::Column,
@@ -288,36 +632,380 @@ fn spawn_level_up_ui(
         background_color: Color::DARK_GRAY.into(),
         ..default()
     };
-    use strum::IntoEnumIterator;
-    let upgrades = &WeaponUpgrade::iter().collect::<Vec<WeaponUpgrade>>();
-    let option_1 = rng.sample(upgrades).unwrap();
-    let mut option_2 = rng.sample(upgrades).unwrap();
 
-    while option_1 == option_2 {
-        option_2 = rng.sample(upgrades).unwrap();
-    }
+    let binding_grid = NodeBundle {
+        style: Style {
+            size: Size::new(Val::Percent(95.0), Val::Percent(70.0)),
+            flex_direction: FlexDirection::Row,
+            flex_wrap: FlexWrap::Wrap,
+            align_content: AlignContent::SpaceAround,
+            justify_content: JustifyContent::SpaceAround,
+            ..default()
+        },
+        ..default()
+    };
 
-    commands.spawn(level_up_parent).with_children(|commands| {
-        commands.spawn(level_up_popup).with_children(|commands| {
-            spawn_button(commands, &asset_server, option_1);
-            spawn_button(commands, &asset_server, option_2);
-        });
-    });
+    let rebinding_stick = rebinding
+        .0
+        .and_then(|index| bindings.bindings.get(index))
+        .is_some_and(|binding| matches!(binding.input, BoundInput::GamepadAxis(_)));
+    let title = if rebinding_stick {
+        "Push a stick... (Esc cancels)"
+    } else if rebinding.0.is_some() {
+        "Press a key or button... (Esc cancels)"
+    } else {
+        "Controls (click to rebind)"
+    };
+
+    let title_text = TextBundle::from_section(
+        title,
+        TextStyle {
+            font: font.clone(),
+            font_size: 40.0,
+            color: Color::rgb(0.9, 0.9, 0.9),
+        },
+    );
+
+    let button_row = NodeBundle {
+        style: Style {
+            size: Size::new(Val::Percent(80.0), Val::Percent(10.0)),
+            flex_direction: FlexDirection::Row,
+            justify_content: JustifyContent::SpaceAround,
+            ..default()
+        },
+        ..default()
+    };
+
+    commands.spawn(controls_parent).with_children(|commands| {
+        commands.spawn(controls_box).with_children(|commands| {
+            commands.spawn(title_text);
+            commands.spawn(binding_grid).with_children(|commands| {
+                for (index, binding) in bindings.bindings.iter().enumerate() {
+                    let label = if rebinding.0 == Some(index) {
+                        format!("{}: ...", binding.action_name())
+                    } else {
+                        format!("{}: {}", binding.action_name(), binding.input.name())
+                    };
+                    let button = (
+                        ButtonBundle {
+                            style: Style {
+                                size: Size::new(Val::Percent(30.0), Val::Percent(12.0)),
+                                justify_content: JustifyContent::Center,
+                                align_items: AlignItems::Center,
+                                ..default()
+                            },
+                            background_color: Color::CRIMSON.into(),
+                            ..default()
+                        },
+                        BindingButtonUI(index),
+                    );
+                    let button_text = TextBundle::from_section(
+                        label,
+                        TextStyle {
+                            font: font.clone(),
+                            font_size: 20.0,
+                            color: Color::rgb(0.9, 0.9, 0.9),
+                        },
+                    );
+                    commands.spawn(button).with_children(|commands| {
+                        commands.spawn(button_text);
+                    });
+                }
+            });
+            commands.spawn(button_row).with_children(|commands| {
+                for (label, reset) in [("Reset", true), ("Back", false)] {
+                    let mut button = commands.spawn(ButtonBundle {
+                        style: Style {
+                            size: Size::new(Val::Percent(40.0), Val::Percent(100.0)),
+                            justify_content: JustifyContent::Center,
+                            align_items: AlignItems::Center,
+                            ..default()
+                        },
+                        background_color: Color::CRIMSON.into(),
+                        ..default()
+                    });
+                    if reset {
+                        button.insert(ControlsResetButton);
+                    } else {
+                        button.insert(ControlsBackButton);
+                    }
+                    button.with_children(|commands| {
+                        commands.spawn(TextBundle::from_section(
+                            label,
+                            TextStyle {
+                                font: font.clone(),
+                                font_size: 32.0,
+                                color: Color::rgb(0.9, 0.9, 0.9),
+                            },
+                        ));
+                    });
+                }
+            });
+        });
+    });
+}
+
+fn about_back_button_system(
+    mut interaction_query: Query<
+        (&Interaction, &mut BackgroundColor),
+        (With<Button>, With<AboutBackButton>),
+    >,
+    mut about: ResMut<AboutShown>,
+    mut next_state: ResMut<NextState<GameState>>,
+) {
+    if !about.0 {
+        return;
+    }
+    for (interaction, mut color) in &mut interaction_query {
+        match *interaction {
+            Interaction::Clicked => {
+                *color = Color::RED.into();
+                about.0 = false;
+            }
+            Interaction::Hovered => {
+                *color = Color::GREEN.into();
+            }
+            Interaction::None => {
+                *color = Color::DARK_GREEN.into();
+            }
+        }
+    }
+}
+
+fn show_about_ui(mut about: Query<&mut Visibility, With<AboutUI>>, about_shown: Res<AboutShown>) {
+    for mut visible in &mut about {
+        if about_shown.0 {
+            *visible = Visibility::Visible;
+        } else {
+            *visible = Visibility::Hidden;
+        }
+    }
+}
+
+fn game_over_button_system(
+    mut interaction_query: Query<
+        (&Interaction, &mut BackgroundColor),
+        (With<Button>, With<GameOverButtonUI>),
+    >,
+    mut next_state: ResMut<NextState<GameState>>,
+) {
+    for (interaction, mut color) in &mut interaction_query {
+        match *interaction {
+            Interaction::Clicked => {
+                *color = Color::RED.into();
+                next_state.set(GameState::MainMenu);
+            }
+            Interaction::Hovered => {
+                *color = Color::GREEN.into();
+            }
+            Interaction::None => {
+                *color = Color::DARK_GREEN.into();
+            }
+        }
+    }
+}
+
+fn despawn_level_up_ui(mut commands: Commands, ui: Query<Entity, With<LevelUpUI>>) {
+    for ui in &ui {
+        commands.entity(ui).despawn_recursive();
+    }
+}
+
+fn despawn_main_menu_ui(mut commands: Commands, ui: Query<Entity, With<MainMenuUI>>) {
+    for ui in &ui {
+        commands.entity(ui).despawn_recursive();
+    }
+}
+
+fn despawn_stage_select_ui(mut commands: Commands, ui: Query<Entity, With<StageSelectUI>>) {
+    for ui in &ui {
+        commands.entity(ui).despawn_recursive();
+    }
+}
+
+fn despawn_character_select_ui(mut commands: Commands, ui: Query<Entity, With<CharacterSelectUI>>) {
+    for ui in &ui {
+        commands.entity(ui).despawn_recursive();
+    }
+}
+
+fn despawn_game_over_ui(mut commands: Commands, ui: Query<Entity, With<GameOverUI>>) {
+    for ui in &ui {
+        commands.entity(ui).despawn_recursive();
+    }
+}
+
+fn show_level_up_ui(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    offers: Res<LevelUpOffers>,
+    inventory: Res<Inventory>,
+    player: Query<(&Player, &Dash)>,
+    whips: Query<&Whip>,
+    ui: Query<Entity, With<LevelUpUI>>,
+) {
+    if !offers.is_changed() {
+        return;
+    }
+    for ui in &ui {
+        commands.entity(ui).despawn_recursive();
+    }
+    let (player, dash) = player.single();
+    let whip_damage = whips.iter().next().map(|whip| whip.damage);
+    spawn_level_up_ui(
+        &mut commands,
+        &asset_server,
+        &offers,
+        &inventory,
+        player,
+        dash,
+        whip_damage,
+    );
 }
 
-fn spawn_button(
+fn spawn_level_up_ui(
+    commands: &mut Commands,
+    asset_server: &AssetServer,
+    offers: &LevelUpOffers,
+    inventory: &Inventory,
+    player: &Player,
+    dash: &Dash,
+    whip_damage: Option<f32>,
+) {
+    let font = asset_server.load("fonts/pointfree.ttf");
+
+    let level_up_parent = (
+        NodeBundle {
+            style: Style {
+                size: Size::new(Val::Percent(100.0), Val::Percent(100.0)),
+                position_type: PositionType::Absolute,
+                align_items: AlignItems::Center,
+                justify_content: JustifyContent::Center,
+                ..default()
+            },
+            ..default()
+        },
+        LevelUpUI,
+    );
+
+    let level_up_popup = NodeBundle {
+        style: Style {
+            size: Size::new(Val::Percent(80.0), Val::Percent(80.0)),
+            position_type: PositionType::Relative,
+            align_items: AlignItems::Center,
+            flex_direction: FlexDirection::Column,
+            justify_content: JustifyContent::SpaceAround,
+            ..default()
+        },
+        background_color: Color::DARK_GRAY.into(),
+        ..default()
+    };
+
+    let title = if offers.banishing {
+        "Pick an upgrade to banish"
+    } else {
+        "Level Up!"
+    };
+
+    let title_text = TextBundle::from_section(
+        title,
+        TextStyle {
+            font: font.clone(),
+            font_size: 40.0,
+            color: Color::rgb(0.9, 0.9, 0.9),
+        },
+    );
+
+    let button_row = NodeBundle {
+        style: Style {
+            size: Size::new(Val::Percent(90.0), Val::Percent(12.0)),
+            flex_direction: FlexDirection::Row,
+            justify_content: JustifyContent::SpaceAround,
+            ..default()
+        },
+        ..default()
+    };
+
+    let card_row = NodeBundle {
+        style: Style {
+            size: Size::new(Val::Percent(95.0), Val::Percent(65.0)),
+            flex_direction: FlexDirection::Row,
+            justify_content: JustifyContent::SpaceAround,
+            ..default()
+        },
+        ..default()
+    };
+
+    let card_width = 92.0 / offers.current.len().max(1) as f32;
+
+    commands.spawn(level_up_parent).with_children(|commands| {
+        commands.spawn(level_up_popup).with_children(|commands| {
+            commands.spawn(title_text);
+            commands.spawn(card_row).with_children(|commands| {
+                for option in &offers.current {
+                    let level = inventory.level(option);
+                    //Stat lines come straight from the effects the upgrade will apply
+                    let stats = option
+                        .describe_effects(level + 1, player, dash, whip_damage)
+                        .join("\n");
+                    spawn_upgrade_card(commands, asset_server, option, level, card_width, stats);
+                }
+            });
+            commands.spawn(button_row).with_children(|commands| {
+                let actions = [
+                    (format!("Reroll ({})", offers.rerolls), 0),
+                    (format!("Skip ({})", offers.skips), 1),
+                    (format!("Banish ({})", offers.banishes), 2),
+                ];
+                for (label, action) in actions {
+                    let mut button = commands.spawn(ButtonBundle {
+                        style: Style {
+                            size: Size::new(Val::Percent(30.0), Val::Percent(100.0)),
+                            justify_content: JustifyContent::Center,
+                            align_items: AlignItems::Center,
+                            ..default()
+                        },
+                        background_color: Color::CRIMSON.into(),
+                        ..default()
+                    });
+                    match action {
+                        0 => button.insert(RerollButtonUI),
+                        1 => button.insert(SkipButtonUI),
+                        _ => button.insert(BanishButtonUI),
+                    };
+                    button.with_children(|commands| {
+                        commands.spawn(TextBundle::from_section(
+                            label,
+                            TextStyle {
+                                font: font.clone(),
+                                font_size: 28.0,
+                                color: Color::rgb(0.9, 0.9, 0.9),
+                            },
+                        ));
+                    });
+                }
+            });
+        });
+    });
+}
+
+fn spawn_upgrade_card(
     commands: &mut ChildBuilder,
     asset_server: &AssetServer,
     weapon: &WeaponUpgrade,
+    level: u32,
+    width: f32,
+    stats: String,
 ) -> Entity {
     let font = asset_server.load("fonts/pointfree.ttf");
-    let button = (
+    let card = (
         ButtonBundle {
             style: Style {
-                size: Size::new(Val::Percent(70.0), Val::Percent(35.0)),
-                justify_content: JustifyContent::Center,
+                size: Size::new(Val::Percent(width), Val::Percent(100.0)),
+                flex_direction: FlexDirection::Column,
+                justify_content: JustifyContent::SpaceEvenly,
                 align_items: AlignItems::Center,
-                align_self: AlignSelf::Center,
+                padding: UiRect::all(Val::Px(8.0)),
                 ..default()
             },
             background_color: Color::CRIMSON.into(),
@@ -326,32 +1014,117 @@ fn spawn_button(
         weapon.clone(),
     );
 
-    let text = weapon.name();
+    let text_style = |font_size: f32, color: Color| TextStyle {
+        font: font.clone(),
+        font_size,
+        color,
+    };
+
+    let level_text = if weapon.is_fallback() {
+        String::new()
+    } else if level == 0 {
+        "NEW!".to_string()
+    } else {
+        format!("Lv {} -> {}", level, level + 1)
+    };
 
-    let button_text = TextBundle::from_section(
-        text,
-        TextStyle {
-            font,
-            font_size: 40.0,
-            color: Color::rgb(0.9, 0.9, 0.9),
-        },
-    );
     commands
-        .spawn(button)
+        .spawn(card)
         .with_children(|commands| {
-            commands.spawn(button_text);
+            if let Some(icon) = weapon.icon() {
+                commands.spawn(ImageBundle {
+                    style: Style {
+                        size: Size::new(Val::Px(64.0), Val::Px(64.0)),
+                        ..default()
+                    },
+                    image: asset_server.load(icon).into(),
+                    ..default()
+                });
+            }
+            commands.spawn(TextBundle::from_section(
+                weapon.name(),
+                text_style(28.0, Color::rgb(0.9, 0.9, 0.9)),
+            ));
+            commands.spawn(TextBundle::from_section(
+                level_text,
+                text_style(24.0, Color::GOLD),
+            ));
+            commands.spawn(
+                TextBundle::from_section(
+                    weapon.description(),
+                    text_style(20.0, Color::rgb(0.9, 0.9, 0.9)),
+                )
+                .with_text_alignment(TextAlignment::Center)
+                .with_style(Style {
+                    max_size: Size::new(Val::Percent(100.0), Val::Auto),
+                    ..default()
+                }),
+            );
+            commands.spawn(
+                TextBundle::from_section(stats, text_style(20.0, Color::rgb(0.7, 1.0, 0.7)))
+                    .with_text_alignment(TextAlignment::Center),
+            );
         })
         .id()
 }
 
-fn player_health_ui_sync(mut ui: Query<&mut Style, With<HealthUI>>, player: Query<&Player>) {
-    let mut style = ui.single_mut();
+//The last run's health would otherwise count as healing on the first frame
+fn reset_health_flash(mut healing: ResMut<HealthFlash>) {
+    *healing = HealthFlash::default();
+}
+
+fn player_health_ui_sync(
+    mut ui: Query<(&mut Style, &mut BackgroundColor), With<HealthUI>>,
+    player: Query<&Player>,
+    mut healing: ResMut<HealthFlash>,
+    time: Res<Time>,
+) {
+    let (mut style, mut color) = ui.single_mut();
     let player = player.single();
 
+    //Skip the jump from an unset player to a fresh run
+    if healing.last_health > 0.0 {
+        healing.healed += (player.health - healing.last_health).max(0.0);
+    }
+    healing.last_health = player.health;
+    //Regen trickles in every frame so only flash once it adds up to something
+    if healing.healed >= 1.0 {
+        healing.healed = 0.0;
+        healing.flash = 0.25;
+    }
+    healing.flash = (healing.flash - time.delta_seconds()).max(0.0);
+
+    *color = if healing.flash > 0.0 {
+        Color::rgb(0.3, 0.95, 0.3).into()
+    } else {
+        Color::RED.into()
+    };
+
     let percent = player.health / player.max_health;
     style.size.width = Val::Percent(percent * 100.0);
 }
 
+fn player_dash_ui_sync(
+    mut ui: Query<(&mut Style, &mut BackgroundColor), With<DashUI>>,
+    player: Query<&Dash>,
+) {
+    let (mut style, mut color) = ui.single_mut();
+    let dash = player.single();
+
+    //Full charges plus however far the next one has recharged
+    let mut charge = dash.charges as f32;
+    if dash.charges < dash.max_charges {
+        charge += dash.recharge.percent();
+    }
+    style.size.width = Val::Percent(charge / dash.max_charges as f32 * 100.0);
+
+    *color = if dash.charges > 0 {
+        Color::rgb(0.4, 0.75, 1.0).into()
+    } else {
+        Color::rgb(0.2, 0.35, 0.5).into()
+    };
+}
+
 fn player_exp_ui_sync(mut ui: Query<&mut Style, With<ExpUI>>, player: Query<&Player>) {
     let mut style = ui.single_mut();
     let player = player.single();
@@ -403,7 +1176,7 @@ fn spawn_player_ui(mut commands: Commands) {
         NodeBundle {
             style: Style {
                 //XXX using Px here because UI isn't based on camera size, just window size
-                size: Size::new(Val::Percent(5.0), Val::Percent(2.0)),
+                size: Size::new(Val::Percent(5.0), Val::Percent(3.0)),
                 position: UiRect {
                     //Player is always centered
                     left: Val::Percent(47.5),
@@ -411,13 +1184,12 @@ fn spawn_player_ui(mut commands: Commands) {
                     top: Val::Percent(60.0),
                     bottom: Val::Auto,
                 },
-                align_items: AlignItems::Center,
+                align_items: AlignItems::FlexStart,
                 justify_content: JustifyContent::FlexStart,
-                flex_direction: FlexDirection::Row,
+                flex_direction: FlexDirection::Column,
                 position_type: PositionType::Absolute,
                 ..default()
             },
-            background_color: BackgroundColor(Color::BLACK),
             ..default()
         },
         GamePlayEntity,
@@ -425,6 +1197,19 @@ fn spawn_player_ui(mut commands: Commands) {
         Name::new("Player UI"),
     );
 
+    let health_track = (
+        NodeBundle {
+            style: Style {
+                size: Size::new(Val::Percent(100.0), Val::Percent(66.0)),
+                flex_direction: FlexDirection::Row,
+                ..default()
+            },
+            background_color: BackgroundColor(Color::BLACK),
+            ..default()
+        },
+        Name::new("Health Track"),
+    );
+
     let health_node = (
         NodeBundle {
             style: Style {
@@ -438,22 +1223,63 @@ fn spawn_player_ui(mut commands: Commands) {
         Name::new("Health UI"),
     );
 
+    let dash_track = (
+        NodeBundle {
+            style: Style {
+                size: Size::new(Val::Percent(100.0), Val::Percent(34.0)),
+                flex_direction: FlexDirection::Row,
+                ..default()
+            },
+            background_color: BackgroundColor(Color::BLACK),
+            ..default()
+        },
+        Name::new("Dash Track"),
+    );
+
+    let dash_node = (
+        NodeBundle {
+            style: Style {
+                size: Size::new(Val::Percent(0.0), Val::Percent(100.0)),
+                ..default()
+            },
+            background_color: BackgroundColor(Color::rgb(0.4, 0.75, 1.0)),
+            ..default()
+        },
+        DashUI,
+        Name::new("Dash UI"),
+    );
+
     commands.spawn(parent_node).with_children(|commands| {
-        commands.spawn(health_node);
+        commands.spawn(health_track).with_children(|commands| {
+            commands.spawn(health_node);
+        });
+        commands.spawn(dash_track).with_children(|commands| {
+            commands.spawn(dash_node);
+        });
     });
 }
 
-fn spawn_main_menu_ui(mut commands: Commands, assets: Res<AssetServer>) {
+fn spawn_main_menu_ui(
+    mut commands: Commands,
+    assets: Res<AssetServer>,
+    aim_mode: Res<AimMode>,
+    stages: Res<Stages>,
+    selected: Res<SelectedStage>,
+    profile: Res<Profile>,
+) {
     let font = assets.load("fonts/pointfree.ttf");
+    let can_continue = run_save_exists();
+    //Squeeze the buttons together when continue is shown
+    let button_height = if can_continue { 10.5 } else { 12.0 };
 
     let menu_parent = (
         NodeBundle {
             style: Style {
-                size: Size::new(Val::Percent(38.0), Val::Percent(32.0)),
+                size: Size::new(Val::Percent(38.0), Val::Percent(58.0)),
                 position: UiRect {
                     left: Val::Percent(47.0),
                     right: Val::Auto,
-                    top: Val::Percent(45.0),
+                    top: Val::Percent(38.0),
                     bottom: Val::Auto,
                 },
                 position_type: PositionType::Absolute,
@@ -471,7 +1297,7 @@ fn spawn_main_menu_ui(mut commands: Commands, assets: Res<AssetServer>) {
     let button = (
         ButtonBundle {
             style: Style {
-                size: Size::new(Val::Percent(70.0), Val::Percent(30.0)),
+                size: Size::new(Val::Percent(70.0), Val::Percent(button_height)),
                 justify_content: JustifyContent::Center,
                 align_items: AlignItems::Center,
                 align_self: AlignSelf::Center,
@@ -505,7 +1331,7 @@ fn spawn_main_menu_ui(mut commands: Commands, assets: Res<AssetServer>) {
     let button_2 = (
         ButtonBundle {
             style: Style {
-                size: Size::new(Val::Percent(70.0), Val::Percent(30.0)),
+                size: Size::new(Val::Percent(70.0), Val::Percent(button_height)),
                 justify_content: JustifyContent::Center,
                 align_items: AlignItems::Center,
                 align_self: AlignSelf::Center,
@@ -520,6 +1346,156 @@ fn spawn_main_menu_ui(mut commands: Commands, assets: Res<AssetServer>) {
 
     let button_text_2 = TextBundle::from_section(
         "About",
+        TextStyle {
+            font: font.clone(),
+            font_size: 40.0,
+            color: Color::rgb(0.9, 0.9, 0.9),
+        },
+    );
+
+    let button_3 = (
+        ButtonBundle {
+            style: Style {
+                size: Size::new(Val::Percent(70.0), Val::Percent(button_height)),
+                justify_content: JustifyContent::Center,
+                align_items: AlignItems::Center,
+                align_self: AlignSelf::Center,
+                ..default()
+            },
+
+            background_color: Color::CRIMSON.into(),
+            ..default()
+        },
+        AimModeButtonUI,
+    );
+
+    let button_text_3 = TextBundle::from_section(
+        aim_mode.name(),
+        TextStyle {
+            font: font.clone(),
+            font_size: 40.0,
+            color: Color::rgb(0.9, 0.9, 0.9),
+        },
+    );
+
+    let button_4 = (
+        ButtonBundle {
+            style: Style {
+                size: Size::new(Val::Percent(70.0), Val::Percent(button_height)),
+                justify_content: JustifyContent::Center,
+                align_items: AlignItems::Center,
+                align_self: AlignSelf::Center,
+                ..default()
+            },
+
+            background_color: Color::CRIMSON.into(),
+            ..default()
+        },
+        ControlsButtonUI,
+    );
+
+    let button_text_4 = TextBundle::from_section(
+        "Controls",
+        TextStyle {
+            font: font.clone(),
+            font_size: 40.0,
+            color: Color::rgb(0.9, 0.9, 0.9),
+        },
+    );
+
+    let button_5 = (
+        ButtonBundle {
+            style: Style {
+                size: Size::new(Val::Percent(70.0), Val::Percent(button_height)),
+                justify_content: JustifyContent::Center,
+                align_items: AlignItems::Center,
+                align_self: AlignSelf::Center,
+                ..default()
+            },
+
+            background_color: Color::CRIMSON.into(),
+            ..default()
+        },
+        StageButtonUI,
+    );
+
+    let button_text_5 = TextBundle::from_section(
+        format!("Stage: {}", selected_stage(&stages, &selected).name),
+        TextStyle {
+            font: font.clone(),
+            font_size: 40.0,
+            color: Color::rgb(0.9, 0.9, 0.9),
+        },
+    );
+
+    let button_6 = (
+        ButtonBundle {
+            style: Style {
+                size: Size::new(Val::Percent(70.0), Val::Percent(button_height)),
+                justify_content: JustifyContent::Center,
+                align_items: AlignItems::Center,
+                align_self: AlignSelf::Center,
+                ..default()
+            },
+
+            background_color: Color::CRIMSON.into(),
+            ..default()
+        },
+        ShopButtonUI,
+    );
+
+    let button_text_6 = TextBundle::from_section(
+        format!("Shop ({}g)", profile.gold),
+        TextStyle {
+            font: font.clone(),
+            font_size: 40.0,
+            color: Color::rgb(0.9, 0.9, 0.9),
+        },
+    );
+
+    let button_7 = (
+        ButtonBundle {
+            style: Style {
+                size: Size::new(Val::Percent(70.0), Val::Percent(button_height)),
+                justify_content: JustifyContent::Center,
+                align_items: AlignItems::Center,
+                align_self: AlignSelf::Center,
+                ..default()
+            },
+
+            background_color: Color::CRIMSON.into(),
+            ..default()
+        },
+        HistoryButtonUI,
+    );
+
+    let button_text_7 = TextBundle::from_section(
+        "High Scores",
+        TextStyle {
+            font: font.clone(),
+            font_size: 40.0,
+            color: Color::rgb(0.9, 0.9, 0.9),
+        },
+    );
+
+    let continue_button = (
+        ButtonBundle {
+            style: Style {
+                size: Size::new(Val::Percent(70.0), Val::Percent(button_height)),
+                justify_content: JustifyContent::Center,
+                align_items: AlignItems::Center,
+                align_self: AlignSelf::Center,
+                ..default()
+            },
+
+            background_color: Color::DARK_GREEN.into(),
+            ..default()
+        },
+        ContinueButtonUI,
+    );
+
+    let continue_text = TextBundle::from_section(
+        "Continue Run",
         TextStyle {
             font,
             font_size: 40.0,
@@ -528,12 +1504,32 @@ fn spawn_main_menu_ui(mut commands: Commands, assets: Res<AssetServer>) {
     );
 
     commands.spawn(menu_parent).with_children(|commands| {
+        if can_continue {
+            commands.spawn(continue_button).with_children(|commands| {
+                commands.spawn(continue_text);
+            });
+        }
         commands.spawn(button).with_children(|commands| {
             commands.spawn(button_text);
         });
         commands.spawn(button_2).with_children(|commands| {
             commands.spawn(button_text_2);
         });
+        commands.spawn(button_3).with_children(|commands| {
+            commands.spawn(button_text_3);
+        });
+        commands.spawn(button_4).with_children(|commands| {
+            commands.spawn(button_text_4);
+        });
+        commands.spawn(button_5).with_children(|commands| {
+            commands.spawn(button_text_5);
+        });
+        commands.spawn(button_6).with_children(|commands| {
+            commands.spawn(button_text_6);
+        });
+        commands.spawn(button_7).with_children(|commands| {
+            commands.spawn(button_text_7);
+        });
     });
 
     commands.spawn((
@@ -553,6 +1549,273 @@ fn spawn_main_menu_ui(mut commands: Commands, assets: Res<AssetServer>) {
     ));
 }
 
+fn spawn_stage_select_ui(mut commands: Commands, assets: Res<AssetServer>, stages: Res<Stages>) {
+    let font = assets.load("fonts/pointfree.ttf");
+
+    let select_parent = (
+        NodeBundle {
+            style: Style {
+                size: Size::new(Val::Percent(100.0), Val::Percent(100.0)),
+                position_type: PositionType::Absolute,
+                align_items: AlignItems::Center,
+                justify_content: JustifyContent::Center,
+                ..default()
+            },
+            ..default()
+        },
+        StageSelectUI,
+        Name::new("Stage Select UI"),
+    );
+
+    let select_box = NodeBundle {
+        style: Style {
+            size: Size::new(Val::Percent(90.0), Val::Percent(85.0)),
+            position_type: PositionType::Relative,
+            align_items: AlignItems::Center,
+            flex_direction: FlexDirection::Column,
+            justify_content: JustifyContent::SpaceAround,
+            ..default()
+        },
+        background_color: Color::DARK_GRAY.into(),
+        ..default()
+    };
+
+    let title_text = TextBundle::from_section(
+        "Choose a stage",
+        TextStyle {
+            font: font.clone(),
+            font_size: 40.0,
+            color: Color::rgb(0.9, 0.9, 0.9),
+        },
+    );
+
+    let card_row = NodeBundle {
+        style: Style {
+            size: Size::new(Val::Percent(95.0), Val::Percent(60.0)),
+            flex_direction: FlexDirection::Row,
+            justify_content: JustifyContent::SpaceAround,
+            ..default()
+        },
+        ..default()
+    };
+
+    let back_button = (
+        ButtonBundle {
+            style: Style {
+                size: Size::new(Val::Percent(30.0), Val::Percent(10.0)),
+                justify_content: JustifyContent::Center,
+                align_items: AlignItems::Center,
+                ..default()
+            },
+            background_color: Color::CRIMSON.into(),
+            ..default()
+        },
+        StageBackButton,
+    );
+
+    let text_style = TextStyle {
+        font: font.clone(),
+        font_size: 20.0,
+        color: Color::rgb(0.9, 0.9, 0.9),
+    };
+
+    commands.spawn(select_parent).with_children(|commands| {
+        commands.spawn(select_box).with_children(|commands| {
+            commands.spawn(title_text);
+            commands.spawn(card_row).with_children(|commands| {
+                let width = 90.0 / stages.0.len() as f32;
+                for (index, stage) in stages.0.iter().enumerate() {
+                    let card = (
+                        ButtonBundle {
+                            style: Style {
+                                size: Size::new(Val::Percent(width), Val::Percent(100.0)),
+                                flex_direction: FlexDirection::Column,
+                                justify_content: JustifyContent::SpaceAround,
+                                align_items: AlignItems::Center,
+                                ..default()
+                            },
+                            background_color: Color::DARK_GREEN.into(),
+                            ..default()
+                        },
+                        StageCardUI(index),
+                    );
+                    let details =
+                        format!("{} map\n{} waves", stage.bounds.name(), stage.waves.len());
+
+                    commands.spawn(card).with_children(|commands| {
+                        commands.spawn(TextBundle::from_section(
+                            stage.name.clone(),
+                            TextStyle {
+                                font_size: 32.0,
+                                ..text_style.clone()
+                            },
+                        ));
+                        commands.spawn(TextBundle::from_section(
+                            stage.description.clone(),
+                            text_style.clone(),
+                        ));
+                        commands.spawn(TextBundle::from_section(details, text_style.clone()));
+                    });
+                }
+            });
+            commands.spawn(back_button).with_children(|commands| {
+                commands.spawn(TextBundle::from_section(
+                    "Back",
+                    TextStyle {
+                        font: font.clone(),
+                        font_size: 32.0,
+                        color: Color::rgb(0.9, 0.9, 0.9),
+                    },
+                ));
+            });
+        });
+    });
+}
+
+fn spawn_character_select_ui(
+    mut commands: Commands,
+    assets: Res<AssetServer>,
+    characters: Res<Characters>,
+) {
+    let font = assets.load("fonts/pointfree.ttf");
+
+    let select_parent = (
+        NodeBundle {
+            style: Style {
+                size: Size::new(Val::Percent(100.0), Val::Percent(100.0)),
+                position_type: PositionType::Absolute,
+                align_items: AlignItems::Center,
+                justify_content: JustifyContent::Center,
+                ..default()
+            },
+            ..default()
+        },
+        CharacterSelectUI,
+        Name::new("Character Select UI"),
+    );
+
+    let select_box = NodeBundle {
+        style: Style {
+            size: Size::new(Val::Percent(90.0), Val::Percent(85.0)),
+            position_type: PositionType::Relative,
+            align_items: AlignItems::Center,
+            flex_direction: FlexDirection::Column,
+            justify_content: JustifyContent::SpaceAround,
+            ..default()
+        },
+        background_color: Color::DARK_GRAY.into(),
+        ..default()
+    };
+
+    let title_text = TextBundle::from_section(
+        "Choose your character",
+        TextStyle {
+            font: font.clone(),
+            font_size: 40.0,
+            color: Color::rgb(0.9, 0.9, 0.9),
+        },
+    );
+
+    let card_row = NodeBundle {
+        style: Style {
+            size: Size::new(Val::Percent(95.0), Val::Percent(65.0)),
+            flex_direction: FlexDirection::Row,
+            justify_content: JustifyContent::SpaceAround,
+            ..default()
+        },
+        ..default()
+    };
+
+    let back_button = (
+        ButtonBundle {
+            style: Style {
+                size: Size::new(Val::Percent(30.0), Val::Percent(10.0)),
+                justify_content: JustifyContent::Center,
+                align_items: AlignItems::Center,
+                ..default()
+            },
+            background_color: Color::CRIMSON.into(),
+            ..default()
+        },
+        CharacterBackButton,
+    );
+
+    let text_style = TextStyle {
+        font: font.clone(),
+        font_size: 20.0,
+        color: Color::rgb(0.9, 0.9, 0.9),
+    };
+
+    commands.spawn(select_parent).with_children(|commands| {
+        commands.spawn(select_box).with_children(|commands| {
+            commands.spawn(title_text);
+            commands.spawn(card_row).with_children(|commands| {
+                //Cards shrink to fit however many characters are defined
+                let width = 90.0 / characters.0.len() as f32;
+                for (index, character) in characters.0.iter().enumerate() {
+                    let card = (
+                        ButtonBundle {
+                            style: Style {
+                                size: Size::new(Val::Percent(width), Val::Percent(100.0)),
+                                flex_direction: FlexDirection::Column,
+                                justify_content: JustifyContent::SpaceAround,
+                                align_items: AlignItems::Center,
+                                ..default()
+                            },
+                            background_color: Color::DARK_GREEN.into(),
+                            ..default()
+                        },
+                        CharacterButtonUI(index),
+                    );
+                    let portrait = ImageBundle {
+                        style: Style {
+                            size: Size::new(Val::Px(65.0), Val::Px(113.0)),
+                            ..default()
+                        },
+                        image: assets.load(&character.sprite_1).into(),
+                        ..default()
+                    };
+                    let stats = format!(
+                        "Health {}  Speed {}\nArmor {}  Regen {}/s\nStarts with {}\n{}",
+                        character.health,
+                        character.speed,
+                        character.armor,
+                        character.regen,
+                        character.starting_weapon.name(),
+                        character.passive.description(),
+                    );
+
+                    commands.spawn(card).with_children(|commands| {
+                        commands.spawn(TextBundle::from_section(
+                            character.name.clone(),
+                            TextStyle {
+                                font_size: 32.0,
+                                ..text_style.clone()
+                            },
+                        ));
+                        commands.spawn(portrait);
+                        commands.spawn(TextBundle::from_section(
+                            character.description.clone(),
+                            text_style.clone(),
+                        ));
+                        commands.spawn(TextBundle::from_section(stats, text_style.clone()));
+                    });
+                }
+            });
+            commands.spawn(back_button).with_children(|commands| {
+                commands.spawn(TextBundle::from_section(
+                    "Back",
+                    TextStyle {
+                        font: font.clone(),
+                        font_size: 32.0,
+                        color: Color::rgb(0.9, 0.9, 0.9),
+                    },
+                ));
+            });
+        });
+    });
+}
+
 fn spawn_game_over_ui(mut commands: Commands, assets: Res<AssetServer>) {
     let font = assets.load("fonts/pointfree.ttf");
 
@@ -1,7 +1,10 @@
 use std::time::Duration;
 
 use crate::{
-    attack::{spawn_area_shot, spawn_close_shot, spawn_whip, whip_attack_facing},
+    attack::{
+        area_shot_targeting, close_shot_targeting, spawn_area_shot, spawn_close_shot, spawn_whip,
+        whip_attack_facing, WHIP_DAMAGE,
+    },
     prelude::*,
 };
 
@@ -15,82 +18,499 @@ impl Plugin for UpgradePlugin {
                 apply_whip_upgrade.after(whip_attack_facing),
                 apply_area_shot_upgrade,
                 apply_close_shot_upgrade,
+                //Apply systems read the level before the pick
+                track_inventory
+                    .after(apply_player_upgrade)
+                    .after(apply_whip_upgrade)
+                    .after(apply_area_shot_upgrade)
+                    .after(apply_close_shot_upgrade),
             )
                 .in_set(OnUpdate(GameState::Gameplay)),
         )
-        .add_event::<UpgradeSelected>();
+        .add_system(reset_level_up_offers.in_schedule(OnEnter(GameState::StartingLoop)))
+        .add_system(roll_level_up_offers.in_schedule(OnEnter(GameState::LevelUp)))
+        .init_resource::<LevelUpOffers>()
+        .init_resource::<Inventory>()
+        .add_event::<UpgradeSelected>()
+        .add_event::<DamageDealt>();
     }
 }
 
-fn apply_player_upgrade(mut reader: EventReader<UpgradeSelected>, mut player: Query<&mut Player>) {
-    let mut player = player.single_mut();
+impl Default for LevelUpOffers {
+    fn default() -> Self {
+        Self {
+            choices: 3,
+            current: Vec::new(),
+            rerolls: 2,
+            skips: 2,
+            banishes: 2,
+            banished: Vec::new(),
+            banishing: false,
+        }
+    }
+}
 
-    for upgrade in reader.iter() {
-        match upgrade.0 {
-            WeaponUpgrade::HealthUp => {
-                let increase = player.max_health * 0.15;
+impl Default for Inventory {
+    fn default() -> Self {
+        Self {
+            weapon_slots: 6,
+            passive_slots: 6,
+            levels: Vec::new(),
+        }
+    }
+}
+
+impl Inventory {
+    pub fn level(&self, upgrade: &WeaponUpgrade) -> u32 {
+        self.levels
+            .iter()
+            .find(|(owned, _)| owned == upgrade)
+            .map_or(0, |(_, level)| *level)
+    }
+
+    pub fn add(&mut self, upgrade: &WeaponUpgrade) {
+        if upgrade.is_fallback() {
+            return;
+        }
+        match self.levels.iter_mut().find(|(owned, _)| owned == upgrade) {
+            Some((_, level)) => *level += 1,
+            None => self.levels.push((upgrade.clone(), 1)),
+        }
+    }
+
+    //Evolutions upgrade a weapon in place so they don't take a slot
+    fn slots_used(&self, weapon: bool) -> usize {
+        self.levels
+            .iter()
+            .filter(|(owned, _)| owned.is_weapon() == weapon && !owned.is_evolution())
+            .count()
+    }
+
+    //Owned and not maxed, or new with a free slot
+    pub fn can_offer(&self, upgrade: &WeaponUpgrade) -> bool {
+        if upgrade.is_fallback() || upgrade.is_evolution() {
+            return false;
+        }
+        let level = self.level(upgrade);
+        if level >= upgrade.max_level() {
+            return false;
+        }
+        let slots = if upgrade.is_weapon() {
+            self.weapon_slots
+        } else {
+            self.passive_slots
+        };
+        level > 0 || self.slots_used(upgrade.is_weapon()) < slots
+    }
+
+    //Maxed weapon, owned catalyst and not evolved yet
+    pub fn evolution_ready(&self, upgrade: &WeaponUpgrade) -> bool {
+        let Some((weapon, catalyst)) = upgrade.evolution() else {
+            return false;
+        };
+        self.level(upgrade) == 0
+            && self.level(&weapon) >= weapon.max_level()
+            && self.level(&catalyst) > 0
+    }
+}
+
+impl WeaponUpgrade {
+    pub fn is_weapon(&self) -> bool {
+        matches!(
+            self,
+            WeaponUpgrade::Whip | WeaponUpgrade::CloseShot | WeaponUpgrade::AreaShot
+        )
+    }
+
+    pub fn is_fallback(&self) -> bool {
+        matches!(self, WeaponUpgrade::Heal | WeaponUpgrade::Gold)
+    }
+
+    pub fn is_evolution(&self) -> bool {
+        self.evolution().is_some()
+    }
+
+    /// Weapon and passive needed before a chest can hold this evolution
+    pub fn evolution(&self) -> Option<(WeaponUpgrade, WeaponUpgrade)> {
+        match self {
+            WeaponUpgrade::SpicyRamen => Some((WeaponUpgrade::Whip, WeaponUpgrade::RegenUp)),
+            WeaponUpgrade::BurritoBarrage => {
+                Some((WeaponUpgrade::CloseShot, WeaponUpgrade::SpeedUp))
+            }
+            WeaponUpgrade::NachoAvalanche => {
+                Some((WeaponUpgrade::AreaShot, WeaponUpgrade::MagnetUp))
+            }
+            _ => None,
+        }
+    }
+
+    pub fn max_level(&self) -> u32 {
+        match self {
+            WeaponUpgrade::Whip => 8,
+            WeaponUpgrade::CloseShot | WeaponUpgrade::AreaShot => 6,
+            WeaponUpgrade::Revive | WeaponUpgrade::DashCharge => 2,
+            WeaponUpgrade::SpicyRamen
+            | WeaponUpgrade::BurritoBarrage
+            | WeaponUpgrade::NachoAvalanche => 1,
+            WeaponUpgrade::Heal | WeaponUpgrade::Gold => u32::MAX,
+            _ => 5,
+        }
+    }
+
+    pub fn description(&self) -> &str {
+        match self {
+            WeaponUpgrade::Whip => "Slaps everything beside you",
+            WeaponUpgrade::CloseShot => "Fires at the nearest enemy",
+            WeaponUpgrade::AreaShot => "Leaves a damaging puddle in crowds",
+            WeaponUpgrade::HealthUp => "More room for pizza",
+            WeaponUpgrade::SpeedUp => "Faster deliveries",
+            WeaponUpgrade::ArmorUp => "Every hit hurts a little less",
+            WeaponUpgrade::RegenUp => "Slowly heal over time",
+            WeaponUpgrade::LifestealUp => "Heal from damage dealt",
+            WeaponUpgrade::MagnetUp => "Pull in orbs from further away",
+            WeaponUpgrade::Revive => "Get back up once when you die",
+            WeaponUpgrade::LuckUp => "Better odds of an extra choice",
+            WeaponUpgrade::DashCooldown => "Dash recharges faster",
+            WeaponUpgrade::DashCharge => "Dash again before landing",
+            WeaponUpgrade::Heal => "Everything is maxed, take a break",
+            WeaponUpgrade::Gold => "Everything is maxed, take the tip",
+            WeaponUpgrade::SpicyRamen => "Ramen hits twice as hard",
+            WeaponUpgrade::BurritoBarrage => "Two more burrito launchers",
+            WeaponUpgrade::NachoAvalanche => "Two more cheese puddles",
+        }
+    }
+
+    pub fn icon(&self) -> Option<&str> {
+        match self {
+            WeaponUpgrade::Whip | WeaponUpgrade::SpicyRamen => Some("ramen.png"),
+            WeaponUpgrade::CloseShot | WeaponUpgrade::BurritoBarrage => Some("burrito.png"),
+            WeaponUpgrade::AreaShot | WeaponUpgrade::NachoAvalanche => Some("nacho.png"),
+            WeaponUpgrade::HealthUp | WeaponUpgrade::Heal => Some("pizza_slice.png"),
+            WeaponUpgrade::MagnetUp => Some("magnet.png"),
+            WeaponUpgrade::Revive => Some("revive_ring.png"),
+            WeaponUpgrade::Gold => Some("gold.png"),
+            _ => None,
+        }
+    }
+
+    /// Effects of reaching `level`, the first pick is level 1
+    pub fn effects(&self, level: u32) -> Vec<UpgradeEffect> {
+        match self {
+            WeaponUpgrade::Whip if level <= 2 => vec![UpgradeEffect::AddEmitter],
+            WeaponUpgrade::Whip => vec![UpgradeEffect::DamagePercent(0.25)],
+            WeaponUpgrade::CloseShot | WeaponUpgrade::AreaShot => vec![UpgradeEffect::AddEmitter],
+            WeaponUpgrade::HealthUp => vec![UpgradeEffect::MaxHealthPercent(0.10)],
+            WeaponUpgrade::SpeedUp => vec![UpgradeEffect::SpeedPercent(0.10)],
+            WeaponUpgrade::ArmorUp => vec![UpgradeEffect::Armor(1.0)],
+            WeaponUpgrade::RegenUp => vec![UpgradeEffect::Regen(0.5)],
+            WeaponUpgrade::LifestealUp => vec![UpgradeEffect::Lifesteal(0.03)],
+            WeaponUpgrade::MagnetUp => vec![UpgradeEffect::MagnetPercent(0.25)],
+            WeaponUpgrade::Revive => vec![UpgradeEffect::Revive],
+            WeaponUpgrade::LuckUp => vec![UpgradeEffect::Luck(0.1)],
+            WeaponUpgrade::DashCooldown => vec![UpgradeEffect::DashCooldownPercent(0.15)],
+            WeaponUpgrade::DashCharge => vec![UpgradeEffect::DashCharge],
+            WeaponUpgrade::Heal => vec![UpgradeEffect::Heal(30.0)],
+            WeaponUpgrade::Gold => vec![UpgradeEffect::Gold(25)],
+            WeaponUpgrade::SpicyRamen => vec![UpgradeEffect::DamagePercent(1.0)],
+            WeaponUpgrade::BurritoBarrage | WeaponUpgrade::NachoAvalanche => {
+                vec![UpgradeEffect::AddEmitter, UpgradeEffect::AddEmitter]
+            }
+        }
+    }
+
+    /// Stat lines for reaching `level`, repeated effects are shown once with their count
+    pub fn describe_effects(
+        &self,
+        level: u32,
+        player: &Player,
+        dash: &Dash,
+        whip_damage: Option<f32>,
+    ) -> Vec<String> {
+        let mut merged: Vec<(UpgradeEffect, u32)> = Vec::new();
+        for effect in self.effects(level) {
+            match merged.last_mut() {
+                Some((last, count)) if *last == effect => *count += 1,
+                _ => merged.push((effect, 1)),
+            }
+        }
+        //Whips carry their own damage, might scales it on hit
+        let damage = whip_damage.unwrap_or(WHIP_DAMAGE) * player.might;
+        merged
+            .iter()
+            .map(|(effect, count)| effect.describe(self, level, *count, damage, player, dash))
+            .collect()
+    }
+
+    //Relative chance of showing up in a level up offer
+    pub fn weight(&self) -> f32 {
+        match self {
+            WeaponUpgrade::Revive => 0.3,
+            WeaponUpgrade::DashCharge => 0.4,
+            WeaponUpgrade::LifestealUp => 0.5,
+            WeaponUpgrade::LuckUp => 0.6,
+            WeaponUpgrade::MagnetUp => 0.8,
+            _ => 1.0,
+        }
+    }
+}
+
+impl UpgradeEffect {
+    pub fn apply(&self, player: &mut Player, dash: &mut Dash) {
+        match *self {
+            //Weapons handle these in their own apply systems
+            UpgradeEffect::AddEmitter | UpgradeEffect::DamagePercent(_) => {}
+            UpgradeEffect::MaxHealthPercent(percent) => {
+                let increase = player.max_health * percent;
                 player.health += increase;
                 player.max_health += increase;
             }
-            WeaponUpgrade::SpeedUp => {
-                player.speed *= 1.10;
+            UpgradeEffect::SpeedPercent(percent) => player.speed *= 1.0 + percent,
+            UpgradeEffect::Armor(amount) => player.armor += amount,
+            UpgradeEffect::Regen(amount) => player.regen += amount,
+            UpgradeEffect::Lifesteal(amount) => player.lifesteal += amount,
+            UpgradeEffect::MagnetPercent(percent) => player.magnet *= 1.0 + percent,
+            UpgradeEffect::Revive => player.revives += 1,
+            UpgradeEffect::Luck(amount) => player.luck += amount,
+            UpgradeEffect::DashCooldownPercent(percent) => {
+                let cooldown = dash.recharge.duration().mul_f32(1.0 - percent);
+                dash.recharge.set_duration(cooldown);
+            }
+            UpgradeEffect::DashCharge => {
+                dash.max_charges += 1;
+                dash.charges += 1;
+            }
+            UpgradeEffect::Heal(amount) => {
+                player.health = (player.health + amount).min(player.max_health);
+            }
+            UpgradeEffect::Gold(amount) => player.gold += amount,
+        }
+    }
+
+    /// Current to next value, `level` is the level being reached and `count` how many times the effect repeats
+    pub fn describe(
+        &self,
+        upgrade: &WeaponUpgrade,
+        level: u32,
+        count: u32,
+        damage: f32,
+        player: &Player,
+        dash: &Dash,
+    ) -> String {
+        match *self {
+            //Evolutions replace a weapon that is already out
+            UpgradeEffect::AddEmitter if upgrade.is_evolution() => {
+                format!("Count +{}", count)
+            }
+            UpgradeEffect::AddEmitter if level == 1 => "Unlocks the weapon".to_string(),
+            UpgradeEffect::AddEmitter => format!("Count {} -> {}", level - 1, level - 1 + count),
+            UpgradeEffect::DamagePercent(percent) => {
+                format!("Damage {:.1} -> {:.1}", damage, damage * (1.0 + percent))
+            }
+            UpgradeEffect::MaxHealthPercent(percent) => format!(
+                "Max Health {:.0} -> {:.0}",
+                player.max_health,
+                player.max_health * (1.0 + percent)
+            ),
+            UpgradeEffect::SpeedPercent(percent) => format!(
+                "Speed {:.2} -> {:.2}",
+                player.speed,
+                player.speed * (1.0 + percent)
+            ),
+            UpgradeEffect::Armor(amount) => {
+                format!("Armor {} -> {}", player.armor, player.armor + amount)
+            }
+            UpgradeEffect::Regen(amount) => format!(
+                "Regen {:.1}/s -> {:.1}/s",
+                player.regen,
+                player.regen + amount
+            ),
+            UpgradeEffect::Lifesteal(amount) => format!(
+                "Lifesteal {:.0}% -> {:.0}%",
+                player.lifesteal * 100.0,
+                (player.lifesteal + amount) * 100.0
+            ),
+            UpgradeEffect::MagnetPercent(percent) => format!(
+                "Pickup Radius {:.2} -> {:.2}",
+                player.magnet,
+                player.magnet * (1.0 + percent)
+            ),
+            UpgradeEffect::Revive => {
+                format!("Extra Lives {} -> {}", player.revives, player.revives + 1)
+            }
+            UpgradeEffect::Luck(amount) => format!(
+                "Luck {:.0}% -> {:.0}%",
+                player.luck * 100.0,
+                (player.luck + amount) * 100.0
+            ),
+            UpgradeEffect::DashCooldownPercent(percent) => {
+                let cooldown = dash.recharge.duration().as_secs_f32();
+                format!(
+                    "Dash Cooldown {:.2}s -> {:.2}s",
+                    cooldown,
+                    cooldown * (1.0 - percent)
+                )
+            }
+            UpgradeEffect::DashCharge => format!(
+                "Dash Charges {} -> {}",
+                dash.max_charges,
+                dash.max_charges + 1
+            ),
+            UpgradeEffect::Heal(amount) => format!(
+                "Health {:.0} -> {:.0}",
+                player.health,
+                (player.health + amount).min(player.max_health)
+            ),
+            UpgradeEffect::Gold(amount) => {
+                format!("Gold {} -> {}", player.gold, player.gold + amount)
             }
-            _ => {}
         }
     }
 }
 
+/// Weighted picks without repeats from everything that isn't banished
+pub fn roll_offers(
+    offers: &mut LevelUpOffers,
+    inventory: &Inventory,
+    luck: f32,
+    rng: &mut GlobalRng,
+) {
+    use strum::IntoEnumIterator;
+    let mut pool: Vec<WeaponUpgrade> = WeaponUpgrade::iter()
+        .filter(|upgrade| !offers.banished.contains(upgrade) && inventory.can_offer(upgrade))
+        .collect();
+    //Everything maxed out, still give the player something
+    if pool.is_empty() {
+        pool = vec![WeaponUpgrade::Heal, WeaponUpgrade::Gold];
+    }
+
+    let mut count = offers.choices;
+    if rng.f32() < luck {
+        count += 1;
+    }
+
+    offers.current.clear();
+    offers.banishing = false;
+    while offers.current.len() < count && !pool.is_empty() {
+        offers.current.push(weighted_pick(&mut pool, rng));
+    }
+}
+
+/// Removes and returns a weighted random upgrade, `pool` must not be empty
+pub fn weighted_pick(pool: &mut Vec<WeaponUpgrade>, rng: &mut GlobalRng) -> WeaponUpgrade {
+    let total: f32 = pool.iter().map(WeaponUpgrade::weight).sum();
+    let mut roll = rng.f32() * total;
+    let mut index = pool.len() - 1;
+    for (i, upgrade) in pool.iter().enumerate() {
+        if roll < upgrade.weight() {
+            index = i;
+            break;
+        }
+        roll -= upgrade.weight();
+    }
+    pool.remove(index)
+}
+
+fn reset_level_up_offers(mut offers: ResMut<LevelUpOffers>) {
+    *offers = LevelUpOffers::default();
+}
+
+fn roll_level_up_offers(
+    mut offers: ResMut<LevelUpOffers>,
+    inventory: Res<Inventory>,
+    player: Query<&Player>,
+    mut rng: ResMut<GlobalRng>,
+) {
+    roll_offers(&mut offers, &inventory, player.single().luck, &mut rng);
+}
+
+fn apply_player_upgrade(
+    mut reader: EventReader<UpgradeSelected>,
+    mut player: Query<(&mut Player, &mut Dash)>,
+    inventory: Res<Inventory>,
+) {
+    let (mut player, mut dash) = player.single_mut();
+
+    for upgrade in reader.iter() {
+        let level = inventory.level(&upgrade.0) + 1;
+        for effect in upgrade.0.effects(level) {
+            effect.apply(&mut player, &mut dash);
+        }
+    }
+}
+
+fn track_inventory(mut reader: EventReader<UpgradeSelected>, mut inventory: ResMut<Inventory>) {
+    for upgrade in reader.iter() {
+        inventory.add(&upgrade.0);
+    }
+}
+
 //XXX messy function
 fn apply_whip_upgrade(
     mut commands: Commands,
     mut reader: EventReader<UpgradeSelected>,
     assets: Res<AssetServer>,
+    inventory: Res<Inventory>,
     mut whips: Query<(&mut Whip, &mut Sprite, &mut Transform)>,
     player: Query<Entity, With<Player>>,
 ) {
     let player = player.single();
 
     for upgrade in reader.iter() {
-        if &UpgradeSelected(WeaponUpgrade::Whip) == upgrade {
+        if matches!(upgrade.0, WeaponUpgrade::Whip | WeaponUpgrade::SpicyRamen) {
             info!("Upgrade whip");
-            // Spawn Whip 1
-            if whips.iter().count() == 0 {
-                let whip_1 = spawn_whip(&mut commands, &assets);
-                commands.entity(player).add_child(whip_1);
-                return;
-            }
-            // Spawn Whip 2 and lock whip 1
-            if let Ok((mut whip, mut sprite, mut transform)) = whips.get_single_mut() {
-                *transform = Transform::from_xyz(-3.5, 0.0, 0.0);
-                sprite.flip_x = false;
-                whip.timer.set_elapsed(Duration::from_secs_f32(0.3));
-
-                let whip_2 = spawn_whip(&mut commands, &assets);
-                commands.entity(player).add_child(whip_2);
-            //Already has 2 whips, just buff damage
-            } else {
-                for (mut whip, _, _) in &mut whips {
-                    whip.damage *= 1.25;
+            let level = inventory.level(&upgrade.0) + 1;
+            for effect in upgrade.0.effects(level) {
+                match effect {
+                    UpgradeEffect::AddEmitter => {
+                        // Spawn Whip 2 and lock whip 1
+                        if let Ok((mut whip, mut sprite, mut transform)) = whips.get_single_mut() {
+                            *transform = Transform::from_xyz(-3.5, 0.0, 0.0);
+                            sprite.flip_x = false;
+                            whip.timer.set_elapsed(Duration::from_secs_f32(0.3));
+                        }
+                        let whip = spawn_whip(&mut commands, &assets);
+                        commands.entity(player).add_child(whip);
+                    }
+                    UpgradeEffect::DamagePercent(percent) => {
+                        for (mut whip, _, _) in &mut whips {
+                            whip.damage *= 1.0 + percent;
+                        }
+                    }
+                    _ => {}
                 }
             }
         }
     }
 }
-
 fn apply_close_shot_upgrade(
     mut commands: Commands,
     mut reader: EventReader<UpgradeSelected>,
-    //TODO upgrade existing sometimes
-    //mut shots: Query<&mut CloseShot>,
+    mut shots: Query<&mut CloseShot>,
+    inventory: Res<Inventory>,
     player: Query<Entity, With<Player>>,
 ) {
     let player = player.single();
 
     for upgrade in reader.iter() {
-        if &UpgradeSelected(WeaponUpgrade::CloseShot) == upgrade {
-            // Spawn new close shot
-            let close_shot = spawn_close_shot(&mut commands);
-            commands.entity(player).add_child(close_shot);
+        if !matches!(
+            upgrade.0,
+            WeaponUpgrade::CloseShot | WeaponUpgrade::BurritoBarrage
+        ) {
+            continue;
+        }
+        let targeting = close_shot_targeting(
+            upgrade.0 == WeaponUpgrade::BurritoBarrage
+                || inventory.level(&WeaponUpgrade::BurritoBarrage) > 0,
+        );
+        for mut shot in &mut shots {
+            shot.targeting = targeting;
+        }
+        let level = inventory.level(&upgrade.0) + 1;
+        for effect in upgrade.0.effects(level) {
+            if effect == UpgradeEffect::AddEmitter {
+                let close_shot = spawn_close_shot(&mut commands, targeting);
+                commands.entity(player).add_child(close_shot);
+            }
         }
     }
 }
@@ -98,17 +518,32 @@ fn apply_close_shot_upgrade(
 fn apply_area_shot_upgrade(
     mut commands: Commands,
     mut reader: EventReader<UpgradeSelected>,
-    //TODO upgrade existing sometimes
-    //mut shots: Query<&mut AreaShot>,
+    mut shots: Query<&mut AreaShot>,
+    inventory: Res<Inventory>,
     player: Query<Entity, With<Player>>,
 ) {
     let player = player.single();
 
     for upgrade in reader.iter() {
-        if &UpgradeSelected(WeaponUpgrade::AreaShot) == upgrade {
-            // Spawn new close shot
-            let area_shot = spawn_area_shot(&mut commands);
-            commands.entity(player).add_child(area_shot);
+        if !matches!(
+            upgrade.0,
+            WeaponUpgrade::AreaShot | WeaponUpgrade::NachoAvalanche
+        ) {
+            continue;
+        }
+        let targeting = area_shot_targeting(
+            upgrade.0 == WeaponUpgrade::NachoAvalanche
+                || inventory.level(&WeaponUpgrade::NachoAvalanche) > 0,
+        );
+        for mut shot in &mut shots {
+            shot.targeting = targeting;
+        }
+        let level = inventory.level(&upgrade.0) + 1;
+        for effect in upgrade.0.effects(level) {
+            if effect == UpgradeEffect::AddEmitter {
+                let area_shot = spawn_area_shot(&mut commands, targeting);
+                commands.entity(player).add_child(area_shot);
+            }
         }
     }
 }